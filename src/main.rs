@@ -1,5 +1,11 @@
 use clap::Parser;
-use std::{fs, hint::black_box, time::Instant};
+use std::{fs, hint::black_box, path::PathBuf, time::Instant};
+
+use solvers_2024::{
+    day09,
+    day14::{self, Heuristic},
+};
+use util::render::{self, FrameFormat, Output, VisualizeConfig};
 
 mod solvers_2024;
 mod solvers_2025;
@@ -113,12 +119,65 @@ struct Args {
     // Run benchmarks instead of executing normally
     #[arg(short, long, default_value_t = false)]
     benchmark: bool,
+
+    // Write frames from days that support it into this directory
+    #[arg(long, value_name = "DIR", conflicts_with = "benchmark")]
+    visualize: Option<PathBuf>,
+
+    // File format of written frames
+    #[arg(long, value_enum, default_value_t = FrameFormat::Png)]
+    frame_format: FrameFormat,
+
+    // Size of a grid cell in pixels for image frames
+    #[arg(long, default_value_t = 4)]
+    frame_scale: usize,
+
+    // Only show every nth frame
+    #[arg(long, default_value_t = 1)]
+    frame_stride: usize,
+
+    // Play frames from days that support it in the terminal
+    #[arg(long, default_value_t = false, conflicts_with_all = ["benchmark", "visualize"])]
+    animate: bool,
+
+    // Frames per second for --animate
+    #[arg(long, default_value_t = 10.0, requires = "animate")]
+    fps: f64,
+
+    // How 2024 day 14 part 2 recognises the christmas tree
+    #[arg(long, value_enum, default_value_t = Heuristic::Variance)]
+    tree_heuristic: Heuristic,
+
+    // Make 2024 day 9 part 2 pack the blocks for the lowest possible checksum
+    #[arg(long, default_value_t = false)]
+    min_checksum: bool,
 }
 fn main() {
     let args = Args::parse();
 
     let year = args.year.unwrap_or(2025);
 
+    let output = if args.animate {
+        Some(Output::Terminal { fps: args.fps })
+    } else {
+        args.visualize.map(|dir| Output::Files {
+            dir,
+            format: args.frame_format,
+            scale: args.frame_scale,
+        })
+    };
+    if let Some(output) = output {
+        render::init(VisualizeConfig {
+            output,
+            stride: args.frame_stride,
+        });
+    }
+
+    day14::select_heuristic(args.tree_heuristic);
+    if args.min_checksum {
+        day09::minimize_checksum();
+    }
+
     let from = args.day.unwrap_or(1);
     let to = args.day.unwrap_or(if year == 2025 { 12 } else { 25 });
     for day in from..=to {
@@ -1,210 +1,280 @@
-use arrayvec::ArrayVec;
-use rustc_hash::FxHashMap;
-
-#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
-enum Direction {
-    Up,
-    Down,
-    Left,
-    Right,
-}
-impl Direction {
-    fn next_dir(self) -> Self {
-        match self {
-            Direction::Up => Direction::Right,
-            Direction::Right => Direction::Down,
-            Direction::Down => Direction::Left,
-            Direction::Left => Direction::Up,
-        }
-    }
-}
+use crate::util::{
+    Direction,
+    render::{Frame, Visualizer},
+};
 
-#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
-struct Coord {
-    y: usize,
-    x: usize,
+// cells are stored as y * width + x
+struct Grid {
+    width: usize,
+    height: usize,
+    blocked: Vec<bool>,
+    start: usize,
 }
-impl Coord {
-    fn in_bounds(&self, square_len: usize) -> bool {
-        self.y < square_len && self.x < square_len
-    }
-    fn apply_dir(&mut self, dir: Direction) {
-        match dir {
-            Direction::Up => self.y = self.y.wrapping_sub(1),
-            Direction::Right => self.x += 1,
-            Direction::Down => self.y += 1,
-            Direction::Left => self.x = self.x.wrapping_sub(1),
+
+impl Grid {
+    fn parse(input: &str) -> Self {
+        let mut start = None;
+        let mut blocked = Vec::new();
+        let width = input.lines().next().map_or(0, str::len);
+        for line in input.lines() {
+            assert_eq!(line.len(), width, "rows have to be the same length");
+            for c in line.chars() {
+                match c {
+                    '#' => blocked.push(true),
+                    '.' => blocked.push(false),
+                    '^' => {
+                        // we save the guard's starting position
+                        start = Some(blocked.len());
+                        blocked.push(false);
+                    }
+                    _ => unreachable!("unrecognised character in day 6 input"),
+                }
+            }
+        }
+        Grid {
+            width,
+            height: blocked.len() / width.max(1),
+            blocked,
+            start: start.expect("there's no guard"),
         }
     }
-    fn next_pos_towards(&self, dir: Direction) -> Self {
+
+    // the cell next to this one, None if that's off the grid
+    fn step(&self, cell: usize, dir: Direction) -> Option<usize> {
+        let (y, x) = (cell / self.width, cell % self.width);
         match dir {
-            Direction::Up => Coord {
-                y: self.y.wrapping_sub(1),
-                x: self.x,
-            },
-            Direction::Right => Coord {
-                y: self.y,
-                x: self.x + 1,
-            },
-            Direction::Down => Coord {
-                y: self.y + 1,
-                x: self.x,
-            },
-            Direction::Left => Coord {
-                y: self.y,
-                x: self.x.wrapping_sub(1),
-            },
+            Direction::Up => (y > 0).then(|| cell - self.width),
+            Direction::Down => (y + 1 < self.height).then(|| cell + self.width),
+            Direction::Left => (x > 0).then(|| cell - 1),
+            Direction::Right => (x + 1 < self.width).then(|| cell + 1),
         }
     }
-    fn get_from<'a>(&self, matrix: &'a Matrix) -> Option<&'a Cell> {
-        matrix.get(self.y).and_then(|it| it.get(self.x))
-    }
 }
 
-fn input_into_matrix(input: String) -> (Coord, Matrix) {
-    // returns input as a matrix using arrayvec(n=130), converted into Cell enum
-    // and also reports the guard spawn point
-    let mut pos = Coord { y: 0, x: 0 };
-
-    let matrix = input
-        .lines()
-        .enumerate()
-        .map(|(y, it)| {
-            it.chars()
-                .enumerate()
-                .map(|(x, it)| {
-                    match it {
-                        '#' => Cell::Blocker,
-                        '.' => Cell::Empty,
-                        '^' => {
-                            // we save the guard's starting position
-                            pos = Coord { y, x };
-                            Cell::Start
-                        }
-                        _ => unreachable!("unrecognised character in day 6 input"),
-                    }
-                })
-                .collect()
-        })
-        // input is max 130x130. if using custom input, change to Vec
-        .collect::<Matrix>();
+// the stop for when the guard walks off the grid instead of running into an
+// obstacle
+const EXIT: u32 = u32::MAX;
 
-    // assert that we have a square
-    assert!(matrix.iter().all(|it| it.len() == matrix.len()));
-
-    (pos, matrix)
+// for every cell and direction, the cell the guard stops at in front of the
+// next obstacle, or EXIT if it walks off the grid. a walk is then one lookup
+// per turn instead of one per step
+struct Jumps<'g> {
+    grid: &'g Grid,
+    stops: [Vec<u32>; 4],
+    // seen[cell * 4 + dir] == generation if that stop was hit in the current
+    // loop check, which saves clearing it every time
+    seen: Vec<u32>,
+    generation: u32,
 }
 
-fn walk(spawn_pos: &Coord, matrix: &Matrix) -> FxHashMap<Coord, Direction> {
-    // finds guard's route by moving forward until we're in front of a wall and have to turn right
-    let mut pos = *spawn_pos;
-    let mut visited = FxHashMap::default();
-    let mut dir = Direction::Up;
-    while pos.in_bounds(matrix.len()) {
-        loop {
-            if pos
-                .next_pos_towards(dir)
-                .get_from(matrix)
-                .is_some_and(|&it| it == Cell::Blocker)
-            {
-                dir = dir.next_dir();
-            } else {
-                break;
+impl<'g> Jumps<'g> {
+    fn new(grid: &'g Grid) -> Self {
+        let cells = grid.blocked.len();
+        let mut stops = [const { Vec::new() }; 4];
+        for dir in Direction::ALL {
+            let stops = &mut stops[dir.index()];
+            stops.resize(cells, EXIT);
+            // the neighbour in the direction has to be done first, up and
+            // left look at lower indices and down and right at higher ones
+            let order: Box<dyn Iterator<Item = usize>> = match dir {
+                Direction::Up | Direction::Left => Box::new(0..cells),
+                Direction::Down | Direction::Right => Box::new((0..cells).rev()),
+            };
+            for cell in order {
+                stops[cell] = match grid.step(cell, dir) {
+                    None => EXIT,
+                    Some(next) if grid.blocked[next] => cell as u32,
+                    Some(next) => stops[next],
+                };
             }
         }
-        visited.insert(pos, dir);
-        pos.apply_dir(dir);
+        Jumps {
+            grid,
+            stops,
+            seen: vec![0; cells * 4],
+            generation: 0,
+        }
     }
-    visited
-}
 
-pub fn part1(input: String) -> String {
-    let (pos, matrix) = input_into_matrix(input);
-
-    walk(&pos, &matrix).len().to_string()
-}
+    // runs f with an extra obstacle on the grid. only the cells lined up
+    // behind it in its row and column change, so those get overwritten and
+    // put back afterwards
+    fn with_obstacle<R>(&mut self, obstacle: usize, f: impl FnOnce(&mut Self) -> R) -> R {
+        let mut undo = Vec::new();
+        for dir in Direction::ALL {
+            let Some(stop) = self.grid.step(obstacle, dir.opposite()) else {
+                continue;
+            };
+            let mut cell = Some(stop);
+            while let Some(c) = cell.filter(|&c| !self.grid.blocked[c]) {
+                undo.push((dir, c, self.stops[dir.index()][c]));
+                self.stops[dir.index()][c] = stop as u32;
+                cell = self.grid.step(c, dir.opposite());
+            }
+        }
+        let out = f(self);
+        for (dir, cell, stop) in undo {
+            self.stops[dir.index()][cell] = stop;
+        }
+        out
+    }
 
-fn idx_for_dir(dir: Direction) -> usize {
-    match dir {
-        Direction::Up => 0,
-        Direction::Right => 1,
-        Direction::Down => 2,
-        Direction::Left => 3,
+    // whether the guard at a cell facing a direction ever gets back to a stop
+    // it was at before, facing the same way. there can be an obstacle right in
+    // front, like the one part 2 puts there, in which case the first stop is
+    // the cell itself and the guard just turns
+    fn loops(&mut self, mut cell: usize, mut dir: Direction) -> bool {
+        self.generation += 1;
+        loop {
+            let stop = self.stops[dir.index()][cell];
+            if stop == EXIT {
+                return false;
+            }
+            cell = stop as usize;
+            let seen = &mut self.seen[cell * 4 + dir.index()];
+            if *seen == self.generation {
+                return true;
+            }
+            *seen = self.generation;
+            dir = dir.clockwise();
+        }
     }
 }
 
-fn test_for_cycle(
-    mut pos: Coord,
-    blocker_pos: Coord,
-    mut direction: Direction,
-    matrix: &Matrix,
-) -> bool {
-    // travel like in p1, but with the additional blocker, and we return true if
-    // we happen to walk in the same direction in the same spot as before
-
-    // 3D. y, x, and direction
-    let mut visited = vec![false; matrix.len() * matrix.len() * 4];
-
-    while pos.in_bounds(matrix.len()) {
-        if visited[pos.y * matrix.len() * 4 + pos.x * 4 + idx_for_dir(direction)] {
-            return true;
+// the guard's route a step at a time, as the cell and the direction it leaves
+// it in
+fn walk(grid: &Grid, mut vis: Option<&mut Visualizer>) -> Vec<(usize, Direction)> {
+    // finds guard's route by moving forward until we're in front of a wall and have to turn right
+    let mut cell = Some(grid.start);
+    let mut route = Vec::new();
+    let mut dir = Direction::Up;
+    let mut frame = vis.as_ref().map(|_| {
+        let mut frame = Frame::new(grid.width, grid.height);
+        for (i, _) in grid.blocked.iter().enumerate().filter(|(_, b)| **b) {
+            frame.set((i / grid.width, i % grid.width), '#');
         }
-        visited[pos.y * matrix.len() * 4 + pos.x * 4 + idx_for_dir(direction)] = true;
+        frame
+    });
+    while let Some(pos) = cell {
         // edge case: multiple blockers near the guard -> turn multiple times
-        loop {
-            let front = pos.next_pos_towards(direction);
-            if front
-                .get_from(matrix)
-                .is_some_and(|&it| it == Cell::Blocker)
-                || front == blocker_pos
-            {
-                direction = direction.next_dir();
-            } else {
-                break;
-            }
+        while grid.step(pos, dir).is_some_and(|next| grid.blocked[next]) {
+            dir = dir.clockwise();
         }
-        pos.apply_dir(direction);
+        route.push((pos, dir));
+        if let (Some(vis), Some(frame)) = (vis.as_deref_mut(), frame.as_mut()) {
+            let pos = (pos / grid.width, pos % grid.width);
+            frame.set(pos, dir.arrow());
+            vis.frame(frame);
+            frame.set(pos, 'X');
+        }
+        cell = grid.step(pos, dir);
     }
-    // we went out of bounds without being in a loop
-    // -> successfully traversed so no cycle
-    false
+    route
 }
 
-#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
-enum Cell {
-    Blocker,
-    Empty,
-    Start,
-}
+pub fn part1(input: String) -> String {
+    let grid = Grid::parse(&input);
 
-type Matrix = ArrayVec<ArrayVec<Cell, 130>, 130>;
+    let mut vis = Visualizer::open("2024_day06_p1");
+    let mut visited = vec![false; grid.blocked.len()];
+    for (cell, _) in walk(&grid, vis.as_mut()) {
+        visited[cell] = true;
+    }
+    visited.into_iter().filter(|&v| v).count().to_string()
+}
 
 pub fn part2(input: String) -> String {
-    let (pos, matrix) = input_into_matrix(input);
-
-    // start with getting p1 answer as it's only useful
-    // to place blockers on the path that the guard goes through
-    let visited = walk(&pos, &matrix);
-    // avoid getting duplicates for blockers in overlapping paths
-    let mut seen = vec![false; 130 * 130];
-    for (spawn_point, spawn_dir) in visited {
-        // test-simulate if we get a loop, and count it if we do
-        let blocker_spot = spawn_point.next_pos_towards(spawn_dir);
-        if blocker_spot.in_bounds(matrix.len())
-            && blocker_spot
-                .get_from(&matrix)
-                .is_some_and(|&c| c != Cell::Blocker)
-            && test_for_cycle(spawn_point, blocker_spot, spawn_dir, &matrix)
-        {
-            seen[blocker_spot.y * 130 + blocker_spot.x] = true;
+    let grid = Grid::parse(&input);
+    let mut jumps = Jumps::new(&grid);
+
+    // it's only useful to place blockers on the path that the guard goes
+    // through. a blocker has to be there before the guard first gets to its
+    // cell, so each one gets tried from the step before that, and cells the
+    // guard has already been through are out
+    let mut tried = vec![false; grid.blocked.len()];
+    let mut loops = 0;
+    for (cell, dir) in walk(&grid, None) {
+        tried[cell] = true;
+        let Some(blocker) = grid.step(cell, dir) else {
+            continue;
+        };
+        if tried[blocker] {
+            continue;
+        }
+        tried[blocker] = true;
+        if jumps.with_obstacle(blocker, |jumps| jumps.loops(cell, dir)) {
+            loops += 1;
         }
     }
-    seen.into_iter().filter(|&v| v).count().to_string()
+    loops.to_string()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::util::TestRng;
+
+    // a random grid with a guard in the middle, and the loop count by walking
+    // every step with each extra obstacle
+    fn brute_force(input: &str) -> usize {
+        let grid = Grid::parse(input);
+        let loops = |blocked: &[bool]| {
+            let mut seen = vec![false; blocked.len() * 4];
+            let (mut cell, mut dir) = (grid.start, Direction::Up);
+            loop {
+                if std::mem::replace(&mut seen[cell * 4 + dir.index()], true) {
+                    return true;
+                }
+                match grid.step(cell, dir) {
+                    None => return false,
+                    Some(next) if blocked[next] => dir = dir.clockwise(),
+                    Some(next) => cell = next,
+                }
+            }
+        };
+        (0..grid.blocked.len())
+            .filter(|&cell| cell != grid.start && !grid.blocked[cell])
+            .filter(|&cell| {
+                let mut blocked = grid.blocked.clone();
+                blocked[cell] = true;
+                loops(&blocked)
+            })
+            .count()
+    }
+
+    #[test]
+    fn random_grids() {
+        let mut rng = TestRng::new(7);
+        for (height, width) in [(8, 8), (12, 20), (25, 9), (30, 30), (1, 5)] {
+            // the guard always gets out in the puzzle, so keep trying until it
+            // does here too
+            let input = loop {
+                let mut input = String::new();
+                for y in 0..height {
+                    for x in 0..width {
+                        let roll = rng.below(100);
+                        input.push(if (y, x) == (height / 2, width / 2) {
+                            '^'
+                        } else if roll < 12 {
+                            '#'
+                        } else {
+                            '.'
+                        });
+                    }
+                    input.push('\n');
+                }
+                let grid = Grid::parse(&input);
+                if !Jumps::new(&grid).loops(grid.start, Direction::Up) {
+                    break input;
+                }
+            };
+            assert_eq!(
+                part2(input.clone()),
+                brute_force(&input).to_string(),
+                "{height}x{width}\n{input}"
+            );
+        }
+    }
 
     #[test]
     fn sample_p1() {
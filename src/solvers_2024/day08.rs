@@ -19,9 +19,8 @@ fn parse_input(input: String) -> (Matrix, BTreeMap<char, Vec<(usize, usize)>>) {
     assert!(matrix.iter().all(|it| it.len() == matrix.len()));
 
     let mut unique_chars: BTreeMap<char, Vec<(usize, usize)>> = BTreeMap::new();
-    for y in 0..matrix.len() {
-        for x in 0..matrix.len() {
-            let c = matrix[y][x];
+    for (y, row) in matrix.iter().enumerate() {
+        for (x, &c) in row.iter().enumerate() {
             if c != '.' {
                 unique_chars.entry(c).or_default().push((y, x));
             }
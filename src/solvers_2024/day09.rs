@@ -1,11 +1,15 @@
 use std::{
-    collections::{BTreeMap, BTreeSet},
-    fmt::Debug,
+    cmp::Reverse,
+    collections::BinaryHeap,
+    fmt::{self, Debug},
+    sync::OnceLock,
 };
 
 use itertools::Itertools;
 
-#[derive(PartialEq, Eq)]
+use crate::util::render::{Frame, Visualizer};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
 enum Space {
     File(usize),
     Free,
@@ -18,186 +22,206 @@ impl Debug for Space {
         }
     }
 }
-fn scan_to_next_free(vec: &[Space], mut it: usize) -> Option<usize> {
-    while let Space::File(_) = vec[it] {
-        it += 1;
-        if it >= vec.len() {
-            return None;
-        }
-    }
-    Some(it)
+
+// how blocks are allowed to move
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Mode {
+    // the last file block goes into the first free block until there are no
+    // gaps left
+    Fragment,
+    // every file moves at most once, highest id first, into the leftmost free
+    // span to the left of it that fits all of it
+    WholeFile,
+    // blocks can go anywhere. the checksum adds up position * id, so it's as
+    // low as it gets with every block packed to the left and the highest ids
+    // first, which also leaves every file in one piece
+    MinChecksum,
 }
 
-pub fn part1(input: String) -> String {
-    let mut vec = Vec::new();
-    input
-        .chars()
-        .filter(char::is_ascii_digit)
-        .chunks(2)
-        .into_iter()
-        .map(|mut chunk| {
-            (
-                // this should never fail
-                chunk.next().unwrap().to_digit(10).unwrap(),
-                // this can be None on end of input
-                chunk.next().unwrap_or('0').to_digit(10).unwrap(),
-            )
-        })
-        .enumerate()
-        .for_each(|(file_id, (file_len, free_len))| {
-            for _ in 0..file_len {
-                vec.push(Space::File(file_id));
+// a run of blocks that are all the same
+#[derive(Clone, Copy)]
+struct Span {
+    space: Space,
+    pos: usize,
+    len: usize,
+}
+
+struct Disk {
+    layout: Vec<Space>,
+}
+
+impl Disk {
+    fn parse(input: &str) -> Self {
+        let mut layout = Vec::new();
+        input
+            .chars()
+            .filter(char::is_ascii_digit)
+            .chunks(2)
+            .into_iter()
+            .map(|mut chunk| {
+                (
+                    // this should never fail,
+                    chunk.next().unwrap().to_digit(10).unwrap(),
+                    // but this can be None on end of input
+                    chunk.next().unwrap_or('0').to_digit(10).unwrap(),
+                )
+            })
+            .enumerate()
+            .for_each(|(file_id, (file_len, free_len))| {
+                layout.extend((0..file_len).map(|_| Space::File(file_id)));
+                layout.extend((0..free_len).map(|_| Space::Free));
+            });
+        Disk { layout }
+    }
+
+    fn spans(&self) -> Vec<Span> {
+        let mut spans: Vec<Span> = Vec::new();
+        for (pos, &space) in self.layout.iter().enumerate() {
+            match spans.last_mut() {
+                Some(span) if span.space == space => span.len += 1,
+                _ => spans.push(Span { space, pos, len: 1 }),
             }
-            for _ in 0..free_len {
-                vec.push(Space::Free);
+        }
+        spans
+    }
+
+    fn checksum(&self) -> usize {
+        self.layout
+            .iter()
+            .enumerate()
+            .map(|(pos, space)| match space {
+                Space::File(id) => pos * id,
+                Space::Free => 0,
+            })
+            .sum()
+    }
+
+    // step gets called with the disk after every move
+    fn compact(&mut self, mode: Mode, mut step: impl FnMut(&Disk)) {
+        match mode {
+            Mode::Fragment => {
+                let (mut left, mut right) = (0, self.layout.len());
+                loop {
+                    while left < right && self.layout[left] != Space::Free {
+                        left += 1;
+                    }
+                    while left < right && self.layout[right - 1] == Space::Free {
+                        right -= 1;
+                    }
+                    if left >= right {
+                        break;
+                    }
+                    self.layout.swap(left, right - 1);
+                    step(self);
+                }
             }
-        });
-
-    // move rightmost file block to the leftmost free block
-    let mut left = Some(0);
-    while left.is_some() {
-        left = scan_to_next_free(&vec, left.unwrap());
-        match left {
-            Some(i) => {
-                let last_block = vec.pop().unwrap();
-                vec[i] = last_block;
+            Mode::WholeFile => {
+                // min-heaps of where the free spans of each length start. a
+                // file of length n can go in any span of length n or more, so
+                // the leftmost one is the smallest of the heap tops from n up,
+                // and whatever's left of the span goes back into a shorter heap
+                let spans = self.spans();
+                let longest = spans.iter().map(|span| span.len).max().unwrap_or(0);
+                let mut free = vec![BinaryHeap::new(); longest + 1];
+                let mut files = Vec::new();
+                for span in spans {
+                    match span.space {
+                        Space::Free => free[span.len].push(Reverse(span.pos)),
+                        Space::File(id) => files.push((id, span)),
+                    }
+                }
+                // the freed up space is never useful, since every file left to
+                // move is further left than it
+                files.sort_by_key(|&(id, span)| Reverse((id, span.pos)));
+                for (_, file) in files {
+                    let Some((pos, len)) = (file.len..free.len())
+                        .filter_map(|len| free[len].peek().map(|&Reverse(pos)| (pos, len)))
+                        .min()
+                        .filter(|&(pos, _)| pos < file.pos)
+                    else {
+                        continue;
+                    };
+                    free[len].pop();
+                    if len > file.len {
+                        free[len - file.len].push(Reverse(pos + file.len));
+                    }
+                    for i in 0..file.len {
+                        self.layout.swap(pos + i, file.pos + i);
+                    }
+                    step(self);
+                }
             }
-            None => {
-                break;
+            Mode::MinChecksum => {
+                let mut blocks = self
+                    .layout
+                    .iter()
+                    .filter_map(|&space| match space {
+                        Space::File(id) => Some(id),
+                        Space::Free => None,
+                    })
+                    .collect::<Vec<_>>();
+                blocks.sort_unstable_by_key(|&id| Reverse(id));
+                let len = self.layout.len();
+                self.layout = blocks.into_iter().map(Space::File).collect();
+                self.layout.resize(len, Space::Free);
+                step(self);
             }
         }
     }
 
-    // checksum
-    vec.into_iter()
-        .enumerate()
-        .map(|(pos, space)| match space {
-            Space::File(id) => pos * id,
-            Space::Free => 0,
-        })
-        .sum::<usize>()
-        .to_string()
+    // the disk wrapped onto rows, with the last digit of the file ids
+    fn frame(&self) -> Frame {
+        const WIDTH: usize = 100;
+        let mut frame = Frame::new(WIDTH, self.layout.len().div_ceil(WIDTH));
+        for (pos, space) in self.layout.iter().enumerate() {
+            if let Space::File(id) = space {
+                let glyph = char::from_digit((id % 10) as u32, 10).unwrap();
+                frame.set((pos / WIDTH, pos % WIDTH), glyph);
+            }
+        }
+        frame
+    }
 }
 
-fn try_move(
-    vec: &mut [Space],
-    buf: &[usize],
-    space_spans: &mut BTreeMap<usize, BTreeSet<usize>>,
-    from_index: usize,
-) -> bool {
-    // flush buffer into first contiguous free fitting span of blocks
-
-    assert!(!buf.is_empty());
-
-    // find first free slot that's to the left of our source
-    let span = space_spans
-        .range_mut(buf.len()..)
-        .filter(|(_, set)| *set.first().unwrap() < from_index)
-        .sorted_by_key(|(_, set)| *set.first().unwrap())
-        .next();
-    if span.is_none() {
-        return false;
+impl fmt::Display for Disk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.layout
+            .iter()
+            .try_for_each(|space| write!(f, "{space:?}"))
     }
+}
 
-    // we get a known span of free space and overwrite it from the start
-    let (&len, idx_set) = span.unwrap();
-    let idx = idx_set.pop_first().unwrap();
-    let mut pointer = idx;
-    for &file_id in buf.iter().rev() {
-        vec[pointer] = Space::File(file_id);
-        pointer += 1;
+fn solve(input: &str, mode: Mode, name: &str) -> String {
+    let mut disk = Disk::parse(input);
+    let mut vis = Visualizer::open(name);
+    if let Some(vis) = &mut vis {
+        vis.frame(&disk.frame());
     }
+    disk.compact(mode, |disk| {
+        if let Some(vis) = &mut vis {
+            vis.frame(&disk.frame());
+        }
+    });
+    disk.checksum().to_string()
+}
 
-    // resize or remove the free span
-    if idx_set.is_empty() {
-        space_spans.remove(&len);
-    }
-    if buf.len() != len {
-        space_spans
-            .entry(len - buf.len())
-            .or_default()
-            .insert(idx + buf.len());
-    }
+pub fn part1(input: String) -> String {
+    solve(&input, Mode::Fragment, "2024_day09_p1")
+}
 
-    for idx in 0..buf.len() {
-        vec[from_index + idx] = Space::Free;
-    }
+static PART2_MODE: OnceLock<Mode> = OnceLock::new();
 
-    true
+// called by the runner for --min-checksum, which makes part 2 find the lowest
+// checksum any layout can have instead of moving whole files
+pub fn minimize_checksum() {
+    PART2_MODE
+        .set(Mode::MinChecksum)
+        .expect("the day 9 mode was set twice");
 }
 
 pub fn part2(input: String) -> String {
-    let mut vec = Vec::new();
-    // map could be an array as lengths seem to be limited to 0-9
-    let mut space_spans: BTreeMap<usize, BTreeSet<usize>> = BTreeMap::new();
-    // assert!(input.len().rem(2) == 1);
-    input
-        .chars()
-        .filter(char::is_ascii_digit)
-        .chunks(2)
-        .into_iter()
-        .map(|mut chunk| {
-            (
-                // this should never fail,
-                chunk.next().unwrap().to_digit(10).unwrap(),
-                // but this can be None on end of input
-                chunk.next().unwrap_or('0').to_digit(10).unwrap(),
-            )
-        })
-        .enumerate()
-        .for_each(|(file_id, (file_len, free_len))| {
-            for _ in 0..file_len {
-                vec.push(Space::File(file_id));
-            }
-            if free_len != 0 {
-                // save start index of the free slot
-                space_spans
-                    .entry(free_len as usize)
-                    .or_default()
-                    .insert(vec.len());
-            }
-            for _ in 0..free_len {
-                vec.push(Space::Free);
-            }
-        });
-
-    // move rightmost file block to the leftmost free block
-    let mut lowest_id_processed = usize::MAX;
-    let mut buf = Vec::new();
-    for right in (0..vec.len()).rev() {
-        match vec[right] {
-            // if buffer isn't empty and we hit a free block, try move
-            Space::Free => {
-                if !buf.is_empty() {
-                    try_move(&mut vec, &buf, &mut space_spans, right + 1);
-                    lowest_id_processed = *buf.last().unwrap();
-                    buf.clear();
-                }
-            }
-            Space::File(id) => {
-                // if buffer isn't empty and we hit a different id file block, try move
-                if buf.last().is_some_and(|it| *it != id) {
-                    try_move(&mut vec, &buf, &mut space_spans, right + 1);
-                    lowest_id_processed = *buf.last().unwrap();
-                    buf.clear();
-                }
-                // avoid moving files twice
-                if id < lowest_id_processed {
-                    buf.push(id);
-                }
-            }
-        }
-    }
-
-    // checksum
-    vec.into_iter()
-        .enumerate()
-        .map(|(pos, space)| match space {
-            Space::File(id) => pos * id,
-            Space::Free => 0,
-        })
-        .sum::<usize>()
-        .to_string()
+    let mode = PART2_MODE.get().copied().unwrap_or(Mode::WholeFile);
+    solve(&input, mode, "2024_day09_p2")
 }
 
 #[cfg(test)]
@@ -210,6 +234,54 @@ mod tests {
         assert_eq!(part1(input), "1928");
     }
 
+    #[test]
+    fn steps() {
+        let mut disk = Disk::parse("12345");
+        let mut layouts = vec![disk.to_string()];
+        disk.compact(Mode::Fragment, |disk| layouts.push(disk.to_string()));
+        assert_eq!(
+            layouts,
+            vec![
+                "0..111....22222",
+                "02.111....2222.",
+                "022111....222..",
+                "0221112...22...",
+                "02211122..2....",
+                "022111222......",
+            ]
+        );
+
+        let mut disk = Disk::parse("2333133121414131402");
+        let mut layouts = Vec::new();
+        disk.compact(Mode::WholeFile, |disk| layouts.push(disk.to_string()));
+        assert_eq!(
+            layouts,
+            vec![
+                "0099.111...2...333.44.5555.6666.777.8888..",
+                "0099.1117772...333.44.5555.6666.....8888..",
+                "0099.111777244.333....5555.6666.....8888..",
+                "00992111777.44.333....5555.6666.....8888..",
+            ]
+        );
+    }
+
+    #[test]
+    fn min_checksum() {
+        let mut disk = Disk::parse("2333133121414131402");
+        disk.compact(Mode::MinChecksum, |_| {});
+        assert_eq!(
+            disk.to_string(),
+            "9988887776666555544333211100.............."
+        );
+        // nothing can beat it, since it's the smallest positions with the
+        // biggest ids
+        for mode in [Mode::Fragment, Mode::WholeFile] {
+            let mut other = Disk::parse("2333133121414131402");
+            other.compact(mode, |_| {});
+            assert!(disk.checksum() < other.checksum(), "{mode:?}");
+        }
+    }
+
     #[test]
     fn sample_p2() {
         let input = "12101".to_string();
@@ -1,47 +1,15 @@
 use regex::Regex;
 
-use crate::util::float_basically_integer;
+use crate::util::linalg::{Matrix, Rational, to_integers};
 
-fn gaussian_elimination(mut matrix: [[f64; 3]; 2]) -> (f64, f64) {
-    // our matrix is
-    // ax bx | x
-    // ay by | y
-
-    // multiplier: ay / ax
-    let f = matrix[1][0] / matrix[0][0];
-    // we negate f * R_1 from R_2, eliminating by
-
-    // ay -= (ay / ax) * ax <=> ay = 0
-    // by -= (ay / ax) * bx
-    // y  -= (ay / ax) * x
-    matrix[1][0] = 0.0;
-    matrix[1][1] -= f * matrix[0][1];
-    matrix[1][2] -= f * matrix[0][2];
-
-    // multiplier: bx / by
-    let f = matrix[0][1] / matrix[1][1];
-    // we negate f * R_2 from R_1, eliminating bx
-
-    // x  -= (bx / by) * y
-    // bx -= (bx / by) * by <=> bx = 0
-    // ax -= (bx / by) * ay
-    matrix[0][2] -= f * matrix[1][2];
-    matrix[0][1] = 0.0;
-    matrix[0][0] -= f * matrix[1][0];
-
-    // now we have to scale both rows so that positions ax and by are equal to 1,
-    // which gets us our answer in x and y
-    (matrix[0][2] / matrix[0][0], matrix[1][2] / matrix[1][1])
-}
-
-fn solve(input: String, constant: f64, threshold_exp: i32) -> u64 {
+fn solve(input: String, constant: i64) -> i128 {
     let button_re = Regex::new(r"Button .: X\+(\d+), Y\+(\d+)").unwrap();
     let prize_re = Regex::new(r"Prize: X=(\d+), Y=(\d+)").unwrap();
     input
         .split("\n\n")
         // trailing newline
         .filter(|&it| !it.is_empty())
-        .map(|block| {
+        .filter_map(|block| {
             let mut it = block.lines();
             let button_a = it.next().unwrap();
             let button_b = it.next().unwrap();
@@ -50,41 +18,32 @@ fn solve(input: String, constant: f64, threshold_exp: i32) -> u64 {
             let (_, [bx, by]) = button_re.captures(button_b).unwrap().extract();
             let (_, [x, y]) = prize_re.captures(prize).unwrap().extract();
 
-            let matrix = [
-                // augmented matrix
-                // ax bx | x
-                // ay by | y
-                [
-                    ax.parse().unwrap(),
-                    bx.parse().unwrap(),
-                    constant + x.parse::<f64>().unwrap(),
-                ],
-                [
-                    ay.parse().unwrap(),
-                    by.parse().unwrap(),
-                    constant + y.parse::<f64>().unwrap(),
-                ],
-            ];
-            gaussian_elimination(matrix)
-        })
-        .filter_map(|pair| {
-            // I wish I had an if-let chain :(
-
-            // threshold has to be adjustable as p2 numbers are too inaccurate,
-            float_basically_integer(pair.0, threshold_exp).and_then(|lhs| {
-                float_basically_integer(pair.1, threshold_exp).map(|rhs| lhs * 3 + rhs)
-            })
+            // ax bx | x
+            // ay by | y
+            let matrix = Matrix::from_rows([
+                [ax.parse::<i64>().unwrap(), bx.parse().unwrap()],
+                [ay.parse().unwrap(), by.parse().unwrap()],
+            ]);
+            let prize = [x, y].map(|it| Rational::from(constant + it.parse::<i64>().unwrap()));
+
+            // buttons pointing in the same direction would have infinitely many
+            // solutions, but the input doesn't seem to have those
+            let presses = matrix.unique_solution(&prize)?;
+            // can't press a button a fractional or negative amount of times
+            match to_integers(&presses)?[..] {
+                [a, b] if a >= 0 && b >= 0 => Some(a * 3 + b),
+                _ => None,
+            }
         })
-        .sum::<u64>()
+        .sum::<i128>()
 }
 
 pub fn part1(input: String) -> String {
-    solve(input, 0.0, -9).to_string()
+    solve(input, 0).to_string()
 }
 
 pub fn part2(input: String) -> String {
-    // threshold has to be higher due to big numbers... kind of stupid
-    solve(input, 10000000000000.0, -3).to_string()
+    solve(input, 10000000000000).to_string()
 }
 
 #[cfg(test)]
@@ -1,6 +1,12 @@
+use std::{collections::VecDeque, sync::OnceLock};
+
+use clap::ValueEnum;
 use regex::Regex;
 
-use crate::util::float_basically_integer;
+use crate::util::{
+    crt,
+    render::{Frame, Visualizer},
+};
 
 struct Robot {
     x: i64,
@@ -9,6 +15,18 @@ struct Robot {
     v_y: i64,
 }
 
+// the area the robots move in. they wrap around at the edges
+#[derive(Clone, Copy)]
+struct Area {
+    w: i64,
+    h: i64,
+}
+
+impl Area {
+    // the real input, which is not part of the input file
+    const FULL: Area = Area { w: 101, h: 103 };
+}
+
 enum Quadrant {
     TopLeft,
     TopRight,
@@ -17,18 +35,27 @@ enum Quadrant {
     Middle,
 }
 
+// how to recognise the christmas tree. variance is enough for the real input,
+// the others can be picked with --tree-heuristic for experimenting
+#[derive(Clone, Copy, PartialEq, Eq, Debug, ValueEnum)]
+pub enum Heuristic {
+    // the tree is where the robot coordinates have the smallest variance
+    Variance,
+    // the tree is where the robot coordinates are most predictable, i.e.
+    // where the coordinate distribution has the least shannon entropy
+    Entropy,
+    // the tree is where the largest group of touching robots is
+    Component,
+}
+
 impl Robot {
-    const H: i64 = 103;
-    const W: i64 = 101;
-    fn iter_by(&mut self, n: usize) {
-        let nx = self.x + n as i64 * self.v_x;
-        self.x = (nx % 101 + 101) % 101;
-        let ny = self.y + n as i64 * self.v_y;
-        self.y = (ny % 103 + 103) % 103;
+    fn iter_by(&mut self, n: usize, area: Area) {
+        self.x = (self.x + n as i64 * self.v_x).rem_euclid(area.w);
+        self.y = (self.y + n as i64 * self.v_y).rem_euclid(area.h);
     }
-    fn get_quadrant(&self) -> Quadrant {
-        let middle_horizontal = Self::W / 2;
-        let middle_vertical = Self::H / 2;
+    fn get_quadrant(&self, area: Area) -> Quadrant {
+        let middle_horizontal = area.w / 2;
+        let middle_vertical = area.h / 2;
 
         if self.x == middle_horizontal || self.y == middle_vertical {
             // the middle horizontal and vertical row are not counted in the safety score
@@ -49,6 +76,15 @@ impl Robot {
     }
 }
 
+fn robot_frame(robots: &[Robot], area: Area) -> Frame {
+    Frame::from_points(
+        area.w as usize,
+        area.h as usize,
+        robots.iter().map(|r| (r.y as usize, r.x as usize)),
+        '#',
+    )
+}
+
 fn parse_input(input: String) -> Vec<Robot> {
     let re = Regex::new(r"p=(\d+),(\d+) v=(-?\d+),(-?\d+)").unwrap();
 
@@ -66,10 +102,10 @@ fn parse_input(input: String) -> Vec<Robot> {
         .collect::<Vec<_>>()
 }
 
-fn calculate_scores(robots: &[Robot]) -> i64 {
+fn calculate_scores(robots: &[Robot], area: Area) -> i64 {
     let mut scores = [0; 4];
     for robot in robots {
-        match robot.get_quadrant() {
+        match robot.get_quadrant(area) {
             Quadrant::TopLeft => {
                 scores[0] += 1;
             }
@@ -90,73 +126,172 @@ fn calculate_scores(robots: &[Robot]) -> i64 {
     scores.into_iter().reduce(|a, b| a * b).unwrap()
 }
 
-pub fn part1(input: String) -> String {
-    let mut robots = parse_input(input);
-
+fn safety_factor(mut robots: Vec<Robot>, area: Area, seconds: usize) -> i64 {
     for robot in &mut robots {
-        robot.iter_by(100);
+        robot.iter_by(seconds, area);
     }
 
-    calculate_scores(&robots).to_string()
-}
-fn vars(bots: &[Robot]) -> (f64, f64) {
-    let n = bots.len() as f64;
-
-    // pass to get means
-    let (sum_x, sum_y) = bots
-        .iter()
-        .map(|&Robot { x, y, .. }| (x, y))
-        .reduce(|(a, b), (c, d)| (a + c, b + d))
-        .unwrap();
-    let mean_x = sum_x as f64 / n;
-    let mean_y = sum_y as f64 / n;
-
-    // pass to get variances
-    bots.iter()
-        .map(|&Robot { x, y, .. }| {
-            let x_var = (x as f64 - mean_x).powi(2) / (n - 1.0);
-            let y_var = (y as f64 - mean_y).powi(2) / (n - 1.0);
-            (x_var, y_var)
+    calculate_scores(&robots, area)
+}
+
+pub fn part1(input: String) -> String {
+    safety_factor(parse_input(input), Area::FULL, 100).to_string()
+}
+
+fn variance(coords: impl Iterator<Item = i64>) -> f64 {
+    // n^2 times the variance, which is all we need for comparisons
+    let (n, sum, sum_sq) = coords.fold((0i128, 0i128, 0i128), |(n, sum, sum_sq), c| {
+        (n + 1, sum + c as i128, sum_sq + (c * c) as i128)
+    });
+    (n * sum_sq - sum * sum) as f64
+}
+
+fn entropy(coords: impl Iterator<Item = i64>, len: i64) -> f64 {
+    let mut counts = vec![0u32; len as usize];
+    for c in coords {
+        counts[c as usize] += 1;
+    }
+    let n = f64::from(counts.iter().sum::<u32>());
+    counts
+        .into_iter()
+        .filter(|&count| count > 0)
+        .map(|count| {
+            let p = f64::from(count) / n;
+            -p * p.log2()
         })
-        .reduce(|(a, b), (c, d)| (a + c, b + d))
-        .unwrap()
+        .sum()
 }
 
-pub fn part2(input: String) -> String {
-    let mut robots = parse_input(input);
-
-    // the robots tend to group along the x or y axis, seemingly with periodicity equal to the width or height
-    // and from these groupings we can observe that the variance in coordinates is minimal when it happens.
-    // we want to find the offset of iteration count when this happens for x and y
-    let mut x_grouping = (0, f64::MAX);
-    let mut y_grouping = (0, f64::MAX);
-    for i in 1..103 {
-        for robot in &mut robots {
-            robot.iter_by(1);
-        }
-        // save minimum variance for x and y coordinates
-        let (x_var, y_var) = vars(&robots);
-        if x_var < x_grouping.1 {
-            x_grouping = (i, x_var);
+fn largest_component(robots: &[Robot], area: Area) -> usize {
+    let (w, h) = (area.w as usize, area.h as usize);
+    let mut occupied = vec![false; w * h];
+    for robot in robots {
+        occupied[robot.y as usize * w + robot.x as usize] = true;
+    }
+
+    // flood fill, clearing cells as we go so that each one is only counted once
+    let mut largest = 0;
+    let mut queue = VecDeque::new();
+    for start in 0..occupied.len() {
+        if !occupied[start] {
+            continue;
         }
-        if y_var < y_grouping.1 {
-            y_grouping = (i, y_var);
+        occupied[start] = false;
+        queue.push_back(start);
+        let mut size = 0;
+        while let Some(idx) = queue.pop_front() {
+            size += 1;
+            let (y, x) = (idx / w, idx % w);
+            let neighbours = [
+                (y > 0).then(|| idx - w),
+                (y + 1 < h).then(|| idx + w),
+                (x > 0).then(|| idx - 1),
+                (x + 1 < w).then(|| idx + 1),
+            ];
+            for next in neighbours.into_iter().flatten() {
+                if occupied[next] {
+                    occupied[next] = false;
+                    queue.push_back(next);
+                }
+            }
         }
+        largest = largest.max(size);
     }
+    largest
+}
+
+// moves robots that are at second `from` to where they are at second `to`.
+// the picture repeats every lcm(w, h) seconds, so going back in time is just
+// going forward far enough
+fn fast_forward(robots: &mut [Robot], area: Area, from: i64, to: i64) {
+    let (_, period) = crt((0, area.w), (0, area.h)).unwrap();
+    let seconds = (to - from).rem_euclid(period) as usize;
+    for robot in robots {
+        robot.iter_by(seconds, area);
+    }
+}
+
+static HEURISTIC: OnceLock<Heuristic> = OnceLock::new();
 
-    // now we have to determine when they group on the y AND x axis at the same time
-    // by solving: x_grouping.0 + 101x = y_grouping.0 + 103y for an integer answer
-    let mut y = 3;
-    loop {
-        let x = (f64::from(y_grouping.0) + 103.0 * f64::from(y) - f64::from(x_grouping.0)) / 101.0;
-        if float_basically_integer(x, -9).is_some() {
-            break;
+// called once by the runner. part 2 uses variance otherwise
+pub fn select_heuristic(heuristic: Heuristic) {
+    HEURISTIC
+        .set(heuristic)
+        .expect("the day 14 heuristic was selected twice");
+}
+
+fn find_tree(mut robots: Vec<Robot>, area: Area, heuristic: Heuristic) -> i64 {
+    let mut vis = Visualizer::open("2024_day14_p2");
+
+    let (answer, elapsed) = match heuristic {
+        Heuristic::Variance | Heuristic::Entropy => {
+            // x coordinates repeat every w seconds and y coordinates every h
+            // seconds. the robots group along the x or y axis when they form
+            // the tree, so we can find the best offset for both axes
+            // separately and combine them afterwards
+            let score = |coords: &mut dyn Iterator<Item = i64>, len| match heuristic {
+                Heuristic::Variance => variance(coords),
+                _ => entropy(coords, len),
+            };
+            let mut x_grouping = (0, f64::MAX);
+            let mut y_grouping = (0, f64::MAX);
+            for i in 0..area.w.max(area.h) {
+                if let Some(vis) = &mut vis {
+                    vis.frame(&robot_frame(&robots, area));
+                }
+                let x_score = score(&mut robots.iter().map(|r| r.x), area.w);
+                if i < area.w && x_score < x_grouping.1 {
+                    x_grouping = (i, x_score);
+                }
+                let y_score = score(&mut robots.iter().map(|r| r.y), area.h);
+                if i < area.h && y_score < y_grouping.1 {
+                    y_grouping = (i, y_score);
+                }
+                for robot in &mut robots {
+                    robot.iter_by(1, area);
+                }
+            }
+
+            // now we have to determine when they group on the y AND x axis at
+            // the same time, which is when
+            // t ≡ x_grouping.0 (mod w) and t ≡ y_grouping.0 (mod h)
+            let (t, _) = crt((x_grouping.0, area.w), (y_grouping.0, area.h))
+                .expect("the x and y groupings never happen at the same time");
+            (t, area.w.max(area.h))
+        }
+        Heuristic::Component => {
+            // the whole picture repeats every lcm(w, h) seconds, so we just
+            // look at all of them
+            let (_, period) = crt((0, area.w), (0, area.h)).unwrap();
+            let mut best = (0, 0);
+            for i in 0..period {
+                if let Some(vis) = &mut vis {
+                    vis.frame(&robot_frame(&robots, area));
+                }
+                let size = largest_component(&robots, area);
+                if size > best.1 {
+                    best = (i, size);
+                }
+                for robot in &mut robots {
+                    robot.iter_by(1, area);
+                }
+            }
+            (best.0, period)
         }
-        y += 1;
+    };
+
+    if let Some(vis) = &mut vis {
+        // move the robots from where the search left them to the tree
+        fast_forward(&mut robots, area, elapsed, answer);
+        vis.frame(&robot_frame(&robots, area));
     }
 
-    // add discovered offset to y times periodicity of 103
-    (y * 103 + y_grouping.0).to_string()
+    answer
+}
+
+pub fn part2(input: String) -> String {
+    let heuristic = HEURISTIC.get().copied().unwrap_or(Heuristic::Variance);
+    find_tree(parse_input(input), Area::FULL, heuristic).to_string()
 }
 
 #[cfg(test)]
@@ -178,7 +313,12 @@ p=7,3 v=-1,2
 p=2,4 v=2,-3
 p=9,5 v=-3,-3"
             .to_string();
-        assert_eq!(part1(input), "21");
+        assert_eq!(part1(input.clone()), "21");
+        // the puzzle text uses a smaller area for this example
+        assert_eq!(
+            safety_factor(parse_input(input), Area { w: 11, h: 7 }, 100),
+            12
+        );
 
         let input = "p=38,34 v=-65,29
 p=8,22 v=-79,-66
@@ -214,8 +354,93 @@ p=34,61 v=-11,65"
         assert_eq!(part1(input), "2640");
     }
 
+    #[test]
+    fn rewind() {
+        let input = "p=0,4 v=3,-3\np=6,3 v=-1,-3\np=10,3 v=-1,2\np=2,0 v=2,-1";
+        let area = Area { w: 11, h: 7 };
+        let positions = |robots: &[Robot]| robots.iter().map(|r| (r.x, r.y)).collect::<Vec<_>>();
+
+        let mut expected = parse_input(input.to_string());
+        for robot in &mut expected {
+            robot.iter_by(2, area);
+        }
+        // the tree can come before the point where the search stopped
+        let mut robots = parse_input(input.to_string());
+        fast_forward(&mut robots, area, 0, 102);
+        fast_forward(&mut robots, area, 102, 2);
+        assert_eq!(positions(&robots), positions(&expected));
+    }
+
     #[test]
     fn sample_p2() {
-        // not practically testable
+        // a small triangle with a trunk that forms after 500 seconds, and some
+        // robots that never join it
+        let input = "p=30,7 v=4,6
+p=7,14 v=-6,-6
+p=3,13 v=3,-4
+p=9,26 v=-6,7
+p=12,32 v=8,-3
+p=18,9 v=-1,6
+p=27,27 v=-3,7
+p=12,8 v=1,8
+p=21,15 v=-1,-6
+p=5,35 v=-6,-7
+p=17,32 v=-1,-1
+p=7,36 v=-6,-9
+p=0,29 v=-4,5
+p=9,9 v=-6,8
+p=14,29 v=-7,5
+p=21,15 v=7,-4
+p=6,12 v=9,4
+p=19,32 v=6,1
+p=16,11 v=7,6
+p=25,30 v=5,5
+p=8,33 v=-6,-1
+p=19,12 v=7,4
+p=25,11 v=-2,6
+p=21,36 v=7,-7
+p=8,29 v=-5,7
+p=14,12 v=-1,6
+p=16,13 v=-9,4
+p=23,1 v=5,-9
+p=1,32 v=3,3
+p=17,34 v=7,-1
+p=3,33 v=3,1
+p=15,35 v=8,-3
+p=18,29 v=-8,9
+p=21,34 v=7,-1
+p=12,13 v=-6,4
+p=13,19 v=-6,-8
+p=27,34 v=-3,1
+p=20,3 v=-9,-9
+p=2,14 v=8,1
+p=29,32 v=8,3
+p=12,19 v=-1,-6
+p=10,25 v=-8,-5
+p=2,14 v=-3,-9
+p=17,11 v=-2,-1
+p=20,7 v=8,-1
+p=28,8 v=-4,-2
+p=20,13 v=-3,2
+p=23,0 v=1,3
+p=12,30 v=-8,9
+p=27,29 v=-4,8
+p=16,27 v=-3,3
+p=20,15 v=7,4
+p=13,35 v=-2,4
+p=15,18 v=9,9
+p=26,36 v=2,7
+p=1,6 v=-8,-2
+p=19,28 v=5,7
+p=19,9 v=2,-7"
+            .to_string();
+        let area = Area { w: 31, h: 37 };
+        for heuristic in [
+            Heuristic::Variance,
+            Heuristic::Entropy,
+            Heuristic::Component,
+        ] {
+            assert_eq!(find_tree(parse_input(input.clone()), area, heuristic), 500);
+        }
     }
 }
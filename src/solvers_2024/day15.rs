@@ -1,241 +1,202 @@
-use crate::util::Direction;
-
-#[derive(Clone, Copy)]
-enum AreaElement {
-    Wall,
-    Box,
-    Box2,
-    Empty,
+use crate::util::{
+    Direction,
+    render::{Frame, Visualizer},
+};
+
+// a box is a solid rectangle, so pushing any part of it moves all of it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Body {
+    // top left corner
+    pos: (usize, usize),
+    height: usize,
+    width: usize,
 }
 
-fn try_move_box(
-    matrix: &mut Vec<Vec<AreaElement>>,
-    coord: (usize, usize),
-    direction: Direction,
-    moving_box: bool,
-    // return true -> bot moves
-    // return false -> bot doesn't move (hits wall)
-) -> bool {
-    let front = direction.apply_unchecked(coord);
-    match matrix[front.0][front.1] {
-        AreaElement::Wall => false,
-        AreaElement::Empty => {
-            if moving_box {
-                matrix[front.0][front.1] = AreaElement::Box;
-            }
-            true
-        }
-        AreaElement::Box => {
-            if try_move_box(matrix, front, direction, true) {
-                if !moving_box {
-                    matrix[front.0][front.1] = AreaElement::Empty;
-                }
-                true
-            } else {
-                // bot encountered a wall so nothing moves
-                false
-            }
-        }
-        _ => unreachable!(),
+impl Body {
+    fn cells(&self) -> impl Iterator<Item = (usize, usize)> + use<> {
+        let Body { pos, height, width } = *self;
+        (pos.0..pos.0 + height).flat_map(move |y| (pos.1..pos.1 + width).map(move |x| (y, x)))
     }
 }
-fn gps_coord(coord: (usize, usize)) -> usize {
-    100 * coord.0 + coord.1
+
+struct Warehouse {
+    height: usize,
+    width: usize,
+    walls: Vec<Vec<bool>>,
+    // which body is on each cell
+    occupant: Vec<Vec<Option<usize>>>,
+    bodies: Vec<Body>,
+    robot: (usize, usize),
 }
-pub fn part1(input: String) -> String {
-    let (area_text, commands_text) = input.split_once("\n\n").unwrap();
-    let mut robot_coord = (0, 0);
-    let mut area = area_text
-        .lines()
-        .enumerate()
-        .map(|(y, line)| {
-            line.chars()
-                .enumerate()
-                .map(|(x, c)| match c {
-                    '#' => AreaElement::Wall,
-                    'O' => AreaElement::Box,
-                    '.' => AreaElement::Empty,
-                    '@' => {
-                        robot_coord = (y, x);
-                        AreaElement::Empty
+
+impl Warehouse {
+    // every cell of the map gets stretched into a scale.0 x scale.1 block, so
+    // a box turns into a body that size. the robot stays a single cell in the
+    // top left of its block
+    fn parse(area_text: &str, scale: (usize, usize)) -> Self {
+        let rows = area_text.lines().collect::<Vec<_>>();
+        let height = rows.len() * scale.0;
+        let width = rows.iter().map(|row| row.len()).max().unwrap_or(0) * scale.1;
+        let mut warehouse = Warehouse {
+            height,
+            width,
+            walls: vec![vec![false; width]; height],
+            occupant: vec![vec![None; width]; height],
+            bodies: Vec::new(),
+            robot: (0, 0),
+        };
+        for (y, line) in rows.iter().enumerate() {
+            for (x, c) in line.chars().enumerate() {
+                let pos = (y * scale.0, x * scale.1);
+                match c {
+                    '#' => {
+                        for row in &mut warehouse.walls[pos.0..pos.0 + scale.0] {
+                            row[pos.1..pos.1 + scale.1].fill(true);
+                        }
                     }
+                    'O' => warehouse.add_body(pos, scale.0, scale.1),
+                    '.' => {}
+                    '@' => warehouse.robot = pos,
                     _ => unreachable!(),
-                })
-                .collect()
-        })
-        .collect::<Vec<Vec<_>>>();
-
-    let commands = commands_text.chars().filter_map(|c| match c {
-        '^' => Some(Direction::Up),
-        'v' => Some(Direction::Down),
-        '<' => Some(Direction::Left),
-        '>' => Some(Direction::Right),
-        '\n' => None,
-        _ => unreachable!("malformed input"),
-    });
+                }
+            }
+        }
+        warehouse
+    }
 
-    for command in commands {
-        if try_move_box(&mut area, robot_coord, command, false) {
-            robot_coord = command.apply_unchecked(robot_coord);
+    fn add_body(&mut self, pos: (usize, usize), height: usize, width: usize) {
+        let body = Body { pos, height, width };
+        let id = self.bodies.len();
+        for (y, x) in body.cells() {
+            assert!(
+                !self.walls[y][x] && self.occupant[y][x].is_none(),
+                "({y}, {x}) is already taken"
+            );
+            self.occupant[y][x] = Some(id);
         }
+        self.bodies.push(body);
     }
 
-    // for line in area.iter() {
-    //     println!(
-    //         "{:?}",
-    //         line.iter()
-    //             .map(|it| match it {
-    //                 AreaElement::Wall => '#',
-    //                 AreaElement::Empty => '.',
-    //                 AreaElement::Box => 'O',
-    //             })
-    //             .join("")
-    //     )
-    // }
-
-    area.iter()
-        .enumerate()
-        .map(|(y, row)| {
-            row.iter()
-                .enumerate()
-                .map(|(x, elem)| match elem {
-                    AreaElement::Box => gps_coord((y, x)),
-                    _ => 0,
-                })
-                .sum::<usize>()
-        })
-        .sum::<usize>()
-        .to_string()
-}
+    // moves the robot, and everything in the way along with it. nothing
+    // moves if any of it would end up in a wall
+    fn push(&mut self, direction: Direction) -> bool {
+        let step = |pos| direction.apply_checked(pos, self.height, self.width);
+        let Some(front) = step(self.robot) else {
+            return false;
+        };
 
-fn move_p2(
-    area: &mut Vec<Vec<AreaElement>>,
-    direction: Direction,
-    coord: (usize, usize),
-    // up/down moves require two passes as otherwise
-    // we might only move half the boxes
-    check_only: bool,
-) -> bool {
-    match area[coord.0][coord.1] {
-        AreaElement::Empty => true,
-        AreaElement::Wall => false,
-        AreaElement::Box => {
-            let front = direction.apply_unchecked(coord);
-            let res = move_p2(area, direction, front, check_only)
-                && move_p2(area, direction, (front.0, front.1 + 1), check_only);
-            if res && !check_only {
-                area[coord.0][coord.1] = AreaElement::Empty;
-                area[coord.0][coord.1 + 1] = AreaElement::Empty;
-                area[front.0][front.1] = AreaElement::Box;
-                area[front.0][front.1 + 1] = AreaElement::Box2;
-                return true;
+        // every body that gets pushed, found by looking at the cells in front
+        // of each one until there's nothing left in the way
+        let mut pushed = Vec::new();
+        let mut seen = vec![false; self.bodies.len()];
+        let mut in_the_way = vec![front];
+        while let Some((y, x)) = in_the_way.pop() {
+            if self.walls[y][x] {
+                return false;
             }
-            res
-        }
-        AreaElement::Box2 => {
-            let front = direction.apply_unchecked(coord);
-            let res = move_p2(area, direction, front, check_only)
-                && move_p2(area, direction, (front.0, front.1 - 1), check_only);
-            if res && !check_only {
-                area[coord.0][coord.1 - 1] = AreaElement::Empty;
-                area[coord.0][coord.1] = AreaElement::Empty;
-                area[front.0][front.1 - 1] = AreaElement::Box;
-                area[front.0][front.1] = AreaElement::Box2;
-                return true;
+            let Some(id) = self.occupant[y][x] else {
+                continue;
+            };
+            if seen[id] {
+                continue;
+            }
+            seen[id] = true;
+            pushed.push(id);
+            for cell in self.bodies[id].cells() {
+                // the edge of the map counts as a wall
+                let Some(next) = step(cell) else {
+                    return false;
+                };
+                if self.occupant[next.0][next.1] != Some(id) {
+                    in_the_way.push(next);
+                }
             }
-            res
         }
-    }
-}
 
-fn move_lr(area: &mut Vec<Vec<AreaElement>>, direction: Direction, coord: (usize, usize)) -> bool {
-    match area[coord.0][coord.1] {
-        AreaElement::Empty => true,
-        AreaElement::Wall => false,
-        AreaElement::Box => {
-            let next = direction.apply_unchecked(coord);
-            if move_lr(area, direction, next) {
-                area[coord.0][coord.1] = AreaElement::Empty;
-                area[next.0][next.1] = AreaElement::Box;
-                return true;
+        // clear everything first so bodies don't overwrite each other
+        for &id in &pushed {
+            for (y, x) in self.bodies[id].cells() {
+                self.occupant[y][x] = None;
             }
-            false
         }
-        AreaElement::Box2 => {
-            let next = direction.apply_unchecked(coord);
-            if move_lr(area, direction, next) {
-                area[coord.0][coord.1] = AreaElement::Empty;
-                area[next.0][next.1] = AreaElement::Box2;
-                return true;
+        for &id in &pushed {
+            let body = &mut self.bodies[id];
+            body.pos = direction.apply_unchecked(body.pos);
+            for (y, x) in body.cells() {
+                self.occupant[y][x] = Some(id);
             }
-            false
         }
+        self.robot = front;
+        true
     }
-}
 
-pub fn part2(input: String) -> String {
-    let (area_text, commands_text) = input.split_once("\n\n").unwrap();
-    let mut robot_coord = (0, 0);
-    let mut area = area_text
-        .lines()
-        .enumerate()
-        .map(|(y, line)| {
-            let mut row = vec![];
-            line.chars().enumerate().for_each(|(x, c)| {
-                let elem = match c {
-                    '#' => [AreaElement::Wall; 2],
-                    'O' => [AreaElement::Box, AreaElement::Box2],
-                    '.' => [AreaElement::Empty; 2],
-                    '@' => {
-                        robot_coord = (y, 2 * x);
-                        [AreaElement::Empty; 2]
-                    }
-                    _ => unreachable!(),
+    // adds up a score for every body
+    fn score(&self, body_score: impl Fn(&Body) -> usize) -> usize {
+        self.bodies.iter().map(body_score).sum()
+    }
+
+    fn frame(&self) -> Frame {
+        let mut frame = Frame::from_grid(&self.walls, |&wall| if wall { '#' } else { '.' });
+        for body in &self.bodies {
+            for (y, x) in body.cells() {
+                let glyph = if body.width == 1 {
+                    'O'
+                } else if x == body.pos.1 {
+                    '['
+                } else if x == body.pos.1 + body.width - 1 {
+                    ']'
+                } else {
+                    '='
                 };
-                row.extend_from_slice(&elem);
-            });
-            row
-        })
-        .collect::<Vec<Vec<_>>>();
+                frame.set((y, x), glyph);
+            }
+        }
+        frame.set(self.robot, '@');
+        frame
+    }
+}
+
+fn gps_coord(coord: (usize, usize)) -> usize {
+    100 * coord.0 + coord.1
+}
 
-    let commands = commands_text.chars().filter_map(|c| match c {
+fn parse_commands(commands_text: &str) -> impl Iterator<Item = Direction> + '_ {
+    commands_text.chars().filter_map(|c| match c {
         '^' => Some(Direction::Up),
         'v' => Some(Direction::Down),
         '<' => Some(Direction::Left),
         '>' => Some(Direction::Right),
         '\n' => None,
         _ => unreachable!("malformed input"),
-    });
-
-    for direction in commands {
-        let next_spot = direction.apply_unchecked(robot_coord);
-        let moved = match direction {
-            Direction::Down | Direction::Up => {
-                if move_p2(&mut area, direction, next_spot, true) {
-                    move_p2(&mut area, direction, next_spot, false)
-                } else {
-                    false
-                }
-            }
-            Direction::Left | Direction::Right => move_lr(&mut area, direction, next_spot),
-        };
-        if moved {
-            robot_coord = next_spot;
+    })
+}
+
+fn simulate(input: &str, scale: (usize, usize), name: &str) -> Warehouse {
+    let (area_text, commands_text) = input.split_once("\n\n").unwrap();
+    let mut warehouse = Warehouse::parse(area_text, scale);
+
+    let mut vis = Visualizer::open(name);
+    if let Some(vis) = &mut vis {
+        vis.frame(&warehouse.frame());
+    }
+    for direction in parse_commands(commands_text) {
+        warehouse.push(direction);
+        if let Some(vis) = &mut vis {
+            vis.frame(&warehouse.frame());
         }
     }
+    warehouse
+}
 
-    area.iter()
-        .enumerate()
-        .map(|(y, row)| {
-            row.iter()
-                .enumerate()
-                .map(|(x, elem)| match elem {
-                    AreaElement::Box => gps_coord((y, x)),
-                    _ => 0,
-                })
-                .sum::<usize>()
-        })
-        .sum::<usize>()
+pub fn part1(input: String) -> String {
+    simulate(&input, (1, 1), "2024_day15_p1")
+        .score(|body| gps_coord(body.pos))
+        .to_string()
+}
+
+pub fn part2(input: String) -> String {
+    simulate(&input, (1, 2), "2024_day15_p2")
+        .score(|body| gps_coord(body.pos))
         .to_string()
 }
 
@@ -283,6 +244,90 @@ v^^>>><<^^<>>^v^<v^vv<>v^<<>^<^v^v><^<<<><<^<v><v<>vv>>v><v^<vv<>v^<<^"
         assert_eq!(part1(input), "10092");
     }
 
+    #[test]
+    fn big_bodies() {
+        let mut warehouse = Warehouse::parse(
+            "########
+#......#
+#..O...#
+#......#
+#......#
+#...@..#
+########",
+            (1, 1),
+        );
+        // a 2x3 body right above the robot, with the box above its left
+        // corner
+        warehouse.add_body((3, 3), 2, 3);
+        assert!(warehouse.push(Direction::Up));
+        // now the box would go into the wall
+        assert!(!warehouse.push(Direction::Up));
+        assert!(warehouse.push(Direction::Right));
+        assert!(!warehouse.push(Direction::Up));
+        assert_eq!(
+            warehouse.frame().to_ascii(),
+            "########
+#..O...#
+#..[=].#
+#..[=].#
+#....@.#
+#......#
+########
+"
+        );
+
+        // around to the left and sideways into the wall
+        for direction in [Direction::Left, Direction::Left, Direction::Left] {
+            assert!(warehouse.push(direction));
+        }
+        assert!(warehouse.push(Direction::Up));
+        assert!(warehouse.push(Direction::Right));
+        assert!(!warehouse.push(Direction::Right));
+        assert_eq!(
+            warehouse.frame().to_ascii(),
+            "########
+#..O...#
+#...[=]#
+#..@[=]#
+#......#
+#......#
+########
+"
+        );
+    }
+
+    #[test]
+    fn scaled() {
+        // everything twice as big both ways, so boxes are 2x2 and every cell
+        // along the front of a push has to be clear
+        let input = "#######
+#.....#
+#.O.O.#
+#..O..#
+#..@..#
+#######
+
+^<^>";
+        let warehouse = simulate(input, (2, 2), "test");
+        assert_eq!(
+            warehouse.frame().to_ascii(),
+            "##############
+##############
+##..........##
+##..........##
+##..[]...[].##
+##..[].[][].##
+##....@[]...##
+##..........##
+##..........##
+##..........##
+##############
+##############
+"
+        );
+        assert_eq!(warehouse.score(|body| body.height * body.width), 12);
+    }
+
     #[test]
     fn sample_p2() {
         let input = "#######
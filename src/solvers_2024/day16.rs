@@ -1,256 +1,310 @@
-use std::collections::BinaryHeap;
-
-use rustc_hash::{FxHashMap, FxHashSet};
-
-use crate::util::Direction;
-
-#[derive(PartialEq)]
-enum Element {
-    Wall,
-    Empty,
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use crate::util::{
+    Direction,
+    render::{Frame, Visualizer},
+};
+
+// what moving a tile forward and turning 90 degrees on the spot cost
+#[derive(Clone, Copy, Debug)]
+struct Costs {
+    step: u64,
+    turn: u64,
 }
 
-#[derive(Debug, PartialEq, Eq)]
-struct Dijk {
-    pos: (usize, usize),
-    cost: u64,
-    dir: Direction,
-    // for p2. probably minimal cost to have it as None for p1
-    path: Option<Vec<(usize, usize)>>,
+const REINDEER: Costs = Costs {
+    step: 1,
+    turn: 1000,
+};
+
+// the reindeer starts on S facing east. dijkstra runs over states, which are a
+// tile and the direction the reindeer is facing, stored as
+// (y * width + x) * 4 + direction
+struct Maze {
+    walls: Vec<Vec<bool>>,
+    width: usize,
+    start: (usize, usize),
+    end: (usize, usize),
+    costs: Costs,
 }
 
-impl Ord for Dijk {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.cost.cmp(&other.cost).reverse()
+impl Maze {
+    fn parse(input: &str, costs: Costs) -> Self {
+        let mut start = None;
+        let mut end = None;
+        let walls = input
+            .lines()
+            .enumerate()
+            .map(|(y, line)| {
+                line.chars()
+                    .enumerate()
+                    .map(|(x, c)| match c {
+                        'S' => {
+                            start = Some((y, x));
+                            false
+                        }
+                        'E' => {
+                            end = Some((y, x));
+                            false
+                        }
+                        '#' => true,
+                        '.' => false,
+                        _ => unreachable!("malformed input"),
+                    })
+                    .collect()
+            })
+            .collect::<Vec<Vec<_>>>();
+        Maze {
+            width: walls[0].len(),
+            walls,
+            start: start.expect("no start"),
+            end: end.expect("no end"),
+            costs,
+        }
     }
-}
 
-impl PartialOrd for Dijk {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.cmp(other))
+    fn state(&self, (y, x): (usize, usize), dir: Direction) -> usize {
+        (y * self.width + x) * 4 + dir.index()
     }
-}
 
-fn turns(direction: &Direction) -> impl Iterator<Item = (Direction, u64)> {
-    match direction {
-        Direction::Down => [
-            (Direction::Down, 0),
-            (Direction::Right, 1000),
-            (Direction::Left, 1000),
-        ]
-        .into_iter(),
-        Direction::Up => [
-            (Direction::Up, 0),
-            (Direction::Right, 1000),
-            (Direction::Left, 1000),
-        ]
-        .into_iter(),
-        Direction::Left => [
-            (Direction::Left, 0),
-            (Direction::Up, 1000),
-            (Direction::Down, 1000),
-        ]
-        .into_iter(),
-        Direction::Right => [
-            (Direction::Right, 0),
-            (Direction::Up, 1000),
-            (Direction::Down, 1000),
-        ]
-        .into_iter(),
+    fn unpack(&self, state: usize) -> ((usize, usize), Direction) {
+        let tile = state / 4;
+        (
+            (tile / self.width, tile % self.width),
+            Direction::ALL[state % 4],
+        )
     }
-}
 
-fn find_path(map: &[Vec<Element>], source: (usize, usize), target: (usize, usize)) -> u64 {
-    // dijkstra's algorithm, but instead of storing costs per 2d index, we want to store
-    // them per (2d index, direction) as turning is treated separately from moving
-    let mut costs = FxHashMap::default();
-    let mut heap = BinaryHeap::new();
-
-    heap.push(Dijk {
-        pos: source,
-        cost: 0,
-        dir: Direction::Right,
-        path: None,
-    });
+    fn start_state(&self) -> usize {
+        self.state(self.start, Direction::Right)
+    }
 
-    while !heap.is_empty() {
-        let u = heap.pop().unwrap();
+    fn end_states(&self) -> [usize; 4] {
+        Direction::ALL.map(|dir| self.state(self.end, dir))
+    }
 
-        if u.pos == target {
-            return u.cost;
-        }
+    // the states a state leads to, or with reverse set the ones that lead
+    // to it. there's no point standing still, so every move is a step in
+    // the direction the reindeer ends up facing, after 0, 1 or 2 turns. the
+    // maze is surrounded by walls so stepping never leaves it
+    fn neighbours(&self, state: usize, reverse: bool) -> impl Iterator<Item = (usize, u64)> + '_ {
+        let (pos, dir) = self.unpack(state);
+        Direction::ALL.into_iter().filter_map(move |other| {
+            let turns = match (dir.index() + 4 - other.index()) % 4 {
+                0 => 0,
+                2 => 2,
+                _ => 1,
+            };
+            let cost = self.costs.step + turns * self.costs.turn;
+            if reverse {
+                // came from a tile behind, facing any direction
+                let (y, x) = dir.opposite().apply_unchecked(pos);
+                (!self.walls[y][x]).then(|| (self.state((y, x), other), cost))
+            } else {
+                let (y, x) = other.apply_unchecked(pos);
+                (!self.walls[y][x]).then(|| (self.state((y, x), other), cost))
+            }
+        })
+    }
 
-        if costs.get(&(u.pos, u.dir)).is_some_and(|&it| it <= u.cost) {
-            continue;
+    // cheapest cost from any of the sources to every state
+    fn distances(&self, sources: &[usize], reverse: bool) -> Vec<u64> {
+        let mut dist = vec![u64::MAX; self.walls.len() * self.width * 4];
+        let mut heap = BinaryHeap::new();
+        for &source in sources {
+            dist[source] = 0;
+            heap.push(Reverse((0, source)));
         }
-
-        costs.insert((u.pos, u.dir), u.cost);
-
-        // at each position we might want to turn to a shorter path
-        // instead of going forward
-        for (new_dir, turn_cost) in turns(&u.dir) {
-            let new_pos = new_dir.apply_unchecked(u.pos);
-
-            // let's not walk into a wall
-            if map[new_pos.0][new_pos.1] != Element::Empty {
+        while let Some(Reverse((cost, state))) = heap.pop() {
+            if cost > dist[state] {
                 continue;
             }
-
-            // there's no point in standing still so we automatically
-            // add 1001 instead of 1000 on a turn
-            let new_cost = u.cost + turn_cost + 1;
-
-            heap.push(Dijk {
-                pos: new_pos,
-                cost: new_cost,
-                dir: new_dir,
-                path: None,
-            });
+            for (next, step) in self.neighbours(state, reverse) {
+                let next_cost = cost + step;
+                if next_cost < dist[next] {
+                    dist[next] = next_cost;
+                    heap.push(Reverse((next_cost, next)));
+                }
+            }
         }
+        dist
     }
 
-    u64::MAX
-}
-pub fn part1(input: String) -> String {
-    let mut start = (0, 0);
-    let mut end = (0, 0);
-    let map = input
-        .lines()
-        .enumerate()
-        .map(|(y, line)| {
-            line.chars()
-                .enumerate()
-                .map(|(x, c)| match c {
-                    'S' => {
-                        start = (y, x);
-                        Element::Empty
-                    }
-                    'E' => {
-                        end = (y, x);
-                        Element::Empty
-                    }
-                    '#' => Element::Wall,
-                    '.' => Element::Empty,
-                    _ => unreachable!("malformed input"),
-                })
-                .collect()
-        })
-        .collect::<Vec<Vec<_>>>();
+    fn best(&self) -> Option<u64> {
+        self.best_in(&self.distances(&[self.start_state()], false))
+    }
 
-    assert_ne!(start, (0, 0));
-    assert_ne!(end, (0, 0));
+    // the best cost from a distance map that starts at the start
+    fn best_in(&self, forward: &[u64]) -> Option<u64> {
+        self.end_states()
+            .into_iter()
+            .map(|state| forward[state])
+            .min()
+            .filter(|&cost| cost != u64::MAX)
+    }
 
-    find_path(&map, start, end).to_string()
-}
+    // a state is on a cheapest path if the cheapest way there plus the
+    // cheapest way from there to the end is the best cost overall, so a
+    // distance map from each side marks every tile without keeping paths
+    fn best_tiles(&self) -> Vec<(usize, usize)> {
+        let forward = self.distances(&[self.start_state()], false);
+        let backward = self.distances(&self.end_states(), true);
+        let Some(best) = self.best_in(&forward) else {
+            return Vec::new();
+        };
+        let mut tiles = (0..forward.len())
+            .filter(|&state| {
+                forward[state]
+                    .checked_add(backward[state])
+                    .is_some_and(|cost| cost == best)
+            })
+            .map(|state| self.unpack(state).0)
+            .collect::<Vec<_>>();
+        tiles.dedup();
+        tiles
+    }
 
-fn p2_paths(
-    map: &[Vec<Element>],
-    source: (usize, usize),
-    target: (usize, usize),
-) -> FxHashSet<(usize, usize)> {
-    // similar to p1, but does not terminate as early due to requiring all shortest paths
-    // and dijk is modified to contain a vector of the path
-    // which is cloned *a lot*
-    // TODO: this could probably be optimised quite a bit by backtracking from the target
-    // instead of keeping countless path vectors that get discarded when the pathfinding goes the wrong way
-    let mut costs = FxHashMap::default();
-    let mut heap = BinaryHeap::new();
-    let mut tiles = FxHashSet::default();
-    let mut shortest_cost = None;
-
-    heap.push(Dijk {
-        pos: source,
-        cost: 0,
-        dir: Direction::Right,
-        path: Some(vec![source]),
-    });
-
-    while !heap.is_empty() {
-        let cur = heap.pop().unwrap();
-
-        if cur.pos == target {
-            if let Some(shortest_cost) = shortest_cost
-                && shortest_cost < cur.cost
-            {
-                continue;
+    // the lowest different costs of getting to the end, at most k of them.
+    // paths stop at the end but can go back over themselves anywhere else, so
+    // if the end can be reached there are k of them as long as steps or turns
+    // cost something and there's a free tile other than the end to double back
+    // on (which means the start has one next to it). with both free every path
+    // costs 0, and with only the end next to the start there's just the one
+    // path. the i-th lowest cost at a state always comes from one of the k
+    // lowest at the state before it, so each state only has to be settled k
+    // times. neither part needs more than the best cost, this is for seeing how
+    // close the runners-up are
+    #[allow(dead_code)]
+    fn k_best(&self, k: usize) -> Vec<u64> {
+        let mut settled: Vec<Vec<u64>> = vec![Vec::new(); self.walls.len() * self.width * 4];
+        let mut heap = BinaryHeap::from([Reverse((0, self.start_state()))]);
+        let ends = self.end_states();
+        let mut out: Vec<u64> = Vec::new();
+        while let Some(Reverse((cost, state))) = heap.pop() {
+            if out.len() == k {
+                break;
             }
-            shortest_cost.replace(cur.cost);
-            tiles.extend(cur.path.unwrap().into_iter());
-            continue;
-        }
-
-        // at each position we might want to turn to a shorter path
-        // instead of going forward
-        for (new_dir, turn_cost) in turns(&cur.dir) {
-            let new_pos = new_dir.apply_unchecked(cur.pos);
-
-            // let's not walk into a wall
-            if map[new_pos.0][new_pos.1] == Element::Wall {
+            let costs = &mut settled[state];
+            if costs.len() == k || costs.last() == Some(&cost) {
                 continue;
             }
-
-            // we automatically add 1001 instead of 1000 on a turn
-            let new_cost = cur.cost + turn_cost + 1;
-
-            let new_key = (new_pos, new_dir);
-            // avoid calculating longer paths to an already as or less optimal calculated spot by skipping this move
-            let existing_cost = costs.get(&new_key);
-            if existing_cost.is_some_and(|&it| it < new_cost) {
+            costs.push(cost);
+            if ends.contains(&state) {
+                // the path is over once it gets to the end
+                if out.last() != Some(&cost) {
+                    out.push(cost);
+                }
                 continue;
             }
+            for (next, step) in self.neighbours(state, false) {
+                heap.push(Reverse((cost + step, next)));
+            }
+        }
+        out
+    }
 
-            costs.insert(new_key, new_cost);
-
-            let mut new_path = cur.path.clone().unwrap();
-            new_path.push(new_pos);
-            heap.push(Dijk {
-                pos: new_pos,
-                cost: new_cost,
-                dir: new_dir,
-                path: Some(new_path),
-            });
+    fn frame(&self, tiles: &[(usize, usize)]) -> Frame {
+        let mut frame = Frame::from_grid(&self.walls, |&wall| if wall { '#' } else { '.' });
+        for &tile in tiles {
+            frame.set(tile, 'O');
         }
+        frame.set(self.start, 'S');
+        frame.set(self.end, 'E');
+        frame
     }
+}
 
-    tiles
+pub fn part1(input: String) -> String {
+    let maze = Maze::parse(&input, REINDEER);
+    maze.best().unwrap().to_string()
 }
 
 pub fn part2(input: String) -> String {
-    let mut start = (0, 0);
-    let mut end = (0, 0);
-    let map = input
-        .lines()
-        .enumerate()
-        .map(|(y, line)| {
-            line.chars()
-                .enumerate()
-                .map(|(x, c)| match c {
-                    'S' => {
-                        start = (y, x);
-                        Element::Empty
-                    }
-                    'E' => {
-                        end = (y, x);
-                        Element::Empty
-                    }
-                    '#' => Element::Wall,
-                    '.' => Element::Empty,
-                    _ => unreachable!("malformed input"),
-                })
-                .collect()
-        })
-        .collect::<Vec<Vec<_>>>();
-
-    assert_ne!(start, (0, 0));
-    assert_ne!(end, (0, 0));
+    let maze = Maze::parse(&input, REINDEER);
+    let tiles = maze.best_tiles();
 
-    let tiles = p2_paths(&map, start, end);
+    if let Some(mut vis) = Visualizer::open("2024_day16_p2") {
+        vis.frame(&maze.frame(&tiles));
+    }
 
     tiles.len().to_string()
 }
 
 #[cfg(test)]
 mod tests {
+    use std::collections::VecDeque;
+
+    use super::{Costs, Maze, REINDEER};
+
+    const SAMPLE: &str = "###############
+#.......#....E#
+#.#.###.#.###.#
+#.....#.#...#.#
+#.###.#####.#.#
+#.#.#.......#.#
+#.#.#####.###.#
+#...........#.#
+###.#.#####.#.#
+#...#.....#.#.#
+#.#.#.###.#.#.#
+#.....#...#.#.#
+#.###.#.#.#.#.#
+#S..#.....#...#
+###############";
+
+    #[test]
+    fn other_costs() {
+        // with free turns it's just the fewest steps
+        let maze = Maze::parse(SAMPLE, Costs { step: 7, turn: 0 });
+        let mut steps = vec![vec![usize::MAX; maze.width]; maze.walls.len()];
+        let mut queue = VecDeque::from([(maze.start, 0)]);
+        while let Some(((y, x), n)) = queue.pop_front() {
+            if maze.walls[y][x] || steps[y][x] != usize::MAX {
+                continue;
+            }
+            steps[y][x] = n;
+            queue.extend([(y - 1, x), (y + 1, x), (y, x - 1), (y, x + 1)].map(|pos| (pos, n + 1)));
+        }
+        let (y, x) = maze.end;
+        assert_eq!(maze.best(), Some(7 * steps[y][x] as u64));
+
+        // turning is all that matters, the sample's best paths turn 7 times
+        let maze = Maze::parse(SAMPLE, Costs { step: 0, turn: 1 });
+        assert_eq!(maze.best(), Some(7));
+    }
+
+    #[test]
+    fn k_best() {
+        let maze = Maze::parse(SAMPLE, REINDEER);
+        let costs = maze.k_best(5);
+        assert_eq!(costs.len(), 5);
+        assert_eq!(costs[0], 7036);
+        assert!(costs.windows(2).all(|pair| pair[0] < pair[1]));
+
+        // a corridor with a way around a block. after going straight and the
+        // detour, the next best is turning around once on the way and
+        // coming back
+        let maze = Maze::parse(
+            "#######
+#S...E#
+#.###.#
+#.....#
+#######",
+            Costs { step: 1, turn: 10 },
+        );
+        assert_eq!(maze.k_best(3), vec![4, 8 + 3 * 10, 6 + 2 * 20]);
+
+        // nothing costs anything, so there's only one cost to give
+        let maze = Maze::parse(SAMPLE, Costs { step: 0, turn: 0 });
+        assert_eq!(maze.k_best(3), vec![0]);
+        // and none without a way to the end
+        let maze = Maze::parse("#####\n#S#E#\n#####", REINDEER);
+        assert!(maze.k_best(3).is_empty());
+        // the only path goes straight into the end, and paths stop there
+        let maze = Maze::parse("####\n#SE#\n####", REINDEER);
+        assert_eq!(maze.k_best(3), vec![1]);
+    }
+
     #[test]
     fn sample_p1() {
         use super::part1;
@@ -260,13 +314,12 @@ mod tests {
             .to_string();
         assert_eq!(part1(input), "4");
 
-        // not sure if this needs fixing. currently you can't turn
-        // 180 degrees in place, but my input doesn't seem to require this?
-        //         let input = "#######
-        // #E...S#
-        // #######"
-        //             .to_string();
-        //         assert_eq!(part1(input), "2004");
+        // turning around on the spot is two turns
+        let input = "#######
+#E...S#
+#######"
+            .to_string();
+        assert_eq!(part1(input), "2004");
 
         let input = "#######
 #S....#
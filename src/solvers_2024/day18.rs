@@ -1,128 +1,229 @@
 use std::collections::VecDeque;
 
-use crate::util::{adjacent_in_bounds, get_2d};
+use crate::util::{
+    UnionFind, adjacent_in_bounds,
+    render::{Frame, Visualizer},
+};
 
-#[derive(Clone, Copy, PartialEq, Eq)]
-enum Tile {
-    Empty,
-    Wall,
+// the real memory space is 71x71 and part 1 looks at it after 1024 bytes
+// have fallen. the example is 7x7 after 12
+const SIZE: usize = 71;
+const FALLEN: usize = 1024;
+
+// bytes fall onto a size x size grid, and the path goes from the top left
+// corner to the bottom right one
+struct Memory {
+    size: usize,
+    bytes: Vec<(usize, usize)>,
+}
+
+// the first byte that cuts the exit off, and a chain of fallen bytes that
+// does the cutting, going from the top or right edge to the left or bottom
+// one. bytes touching diagonally count as a chain since there's no squeezing
+// between them. the blocking byte is always in it, as nothing before it cut
+// anything off
+#[derive(Debug)]
+struct Cut {
+    byte: usize,
+    chain: Vec<(usize, usize)>,
 }
 
-fn find_shortest_path(
-    area: &[Vec<Tile>],
-    source: (usize, usize),
-    target: (usize, usize),
-) -> Option<u64> {
-    // simple bfs
-    let mut heap = VecDeque::new();
-    let mut seen = vec![false; area.len() * area.len()];
-    seen[source.0 * area.len() + source.1] = true;
-
-    heap.push_back((0, source));
-
-    while let Some((current_cost, current_pos)) = heap.pop_front() {
-        if current_pos == target {
-            return Some(current_cost);
+impl Memory {
+    fn parse(input: &str, size: usize) -> Self {
+        let bytes = input
+            .lines()
+            .map(|line| {
+                let (lhs, rhs) = line.split_once(',').unwrap();
+                (lhs.parse::<usize>().unwrap(), rhs.parse::<usize>().unwrap())
+            })
+            .collect();
+        Memory { size, bytes }
+    }
+
+    fn index(&self, (y, x): (usize, usize)) -> usize {
+        y * self.size + x
+    }
+
+    fn exit(&self) -> (usize, usize) {
+        (self.size - 1, self.size - 1)
+    }
+
+    fn walls(&self, fallen: usize) -> Vec<bool> {
+        let mut walls = vec![false; self.size * self.size];
+        for &byte in self.bytes.iter().take(fallen) {
+            walls[self.index(byte)] = true;
         }
+        walls
+    }
 
-        for (y, x) in adjacent_in_bounds(current_pos.0, current_pos.1, area.len()) {
-            // let's not walk into a wall
-            if area[y][x] == Tile::Wall || seen[y * area.len() + x] {
+    fn shortest_path(&self, fallen: usize) -> Option<u64> {
+        // simple bfs
+        let walls = self.walls(fallen);
+        let mut queue = VecDeque::from([(0, (0, 0))]);
+        let mut seen = vec![false; walls.len()];
+        while let Some((cost, pos)) = queue.pop_front() {
+            if walls[self.index(pos)] || std::mem::replace(&mut seen[self.index(pos)], true) {
                 continue;
             }
-            seen[y * area.len() + x] = true;
+            if pos == self.exit() {
+                return Some(cost);
+            }
+            queue.extend(adjacent_in_bounds(pos.0, pos.1, self.size).map(|next| (cost + 1, next)));
+        }
+        None
+    }
 
-            heap.push_back((current_cost + 1, (y, x)));
+    // once the exit is cut off it stays that way, so the number of fallen
+    // bytes where that happens can be binary searched. part 2 goes with the
+    // union find, so this is only there for the tests to check it against
+    #[cfg(test)]
+    fn first_blocking_search(&self) -> Option<usize> {
+        // reachable with lo bytes fallen, not with hi
+        self.shortest_path(0)?;
+        let (mut lo, mut hi) = (0, self.bytes.len() + 1);
+        while hi - lo > 1 {
+            let mid = (lo + hi) / 2;
+            if mid <= self.bytes.len() && self.shortest_path(mid).is_some() {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
         }
+        (hi <= self.bytes.len()).then(|| hi - 1)
     }
 
-    None
-}
+    // lets every byte fall, then takes them away again from the last one,
+    // joining up the free tiles with a union find. the byte whose removal
+    // connects the start to the exit is the one that blocked it
+    fn first_blocking_union_find(&self) -> Option<usize> {
+        let cells = self.size * self.size;
+        // bytes can fall on the same tile more than once
+        let mut fallen = vec![0; cells];
+        for &byte in &self.bytes {
+            fallen[self.index(byte)] += 1;
+        }
+        let mut uf = UnionFind::new(cells);
+        let free = |uf: &mut UnionFind, fallen: &[u32], pos: (usize, usize)| {
+            for next in adjacent_in_bounds(pos.0, pos.1, self.size) {
+                if fallen[self.index(next)] == 0 {
+                    uf.unite(self.index(pos), self.index(next));
+                }
+            }
+        };
+        for y in 0..self.size {
+            for x in 0..self.size {
+                if fallen[self.index((y, x))] == 0 {
+                    free(&mut uf, &fallen, (y, x));
+                }
+            }
+        }
 
-fn has_path(area: &[Vec<Tile>], source: (usize, usize), target: (usize, usize)) -> bool {
-    // DFS.
-    // [bool] seems to be slightly faster than a bitarr here
-    let mut seen = [false; 71 * 71];
-    let mut stack = vec![source];
-    while let Some(current) = stack.pop() {
-        let seen_idx = current.0 * area.len() + current.1;
-        if seen[seen_idx] {
-            continue;
+        let (start, exit) = (0, self.index(self.exit()));
+        let open = |uf: &mut UnionFind, fallen: &[u32]| {
+            fallen[start] == 0 && fallen[exit] == 0 && uf.connected(start, exit)
+        };
+        if open(&mut uf, &fallen) {
+            return None;
         }
-        seen[seen_idx] = true;
-        for (y, x) in adjacent_in_bounds(current.0, current.1, area.len()) {
-            // let's not walk inside a wall
-            if area[y][x] == Tile::Wall {
-                continue;
+        for (i, &byte) in self.bytes.iter().enumerate().rev() {
+            let cell = &mut fallen[self.index(byte)];
+            *cell -= 1;
+            if *cell == 0 {
+                free(&mut uf, &fallen, byte);
             }
-            if (y, x) == target {
-                return true;
+            if open(&mut uf, &fallen) {
+                return Some(i);
             }
-            stack.push((y, x));
         }
+        unreachable!("the start and exit are connected with nothing fallen")
     }
-    false
-}
 
-fn parse_input(input: String) -> (Vec<(usize, usize)>, Vec<Vec<Tile>>) {
-    let mut bytes = input.lines().map(|line| {
-        let (lhs, rhs) = line.split_once(',').unwrap();
-        (lhs.parse::<usize>().unwrap(), rhs.parse::<usize>().unwrap())
-    });
-    let mut area = vec![vec![Tile::Empty; 71]; 71];
-
-    // p1 guarantees that the first 1024 bytes don't block p2, so we can consume
-    // the same amount for p1 and p2
-    for (y, x) in bytes.by_ref().take(1024) {
-        area[y][x] = Tile::Wall;
+    fn cut(&self) -> Option<Cut> {
+        let byte = self.first_blocking_union_find()?;
+        let walls = self.walls(byte + 1);
+        let last = self.size - 1;
+        // a bfs from walls on the top and right edges to ones on the left and
+        // bottom edges, going diagonally too
+        let mut queue = VecDeque::new();
+        let mut previous = vec![None; walls.len()];
+        for i in 0..self.size {
+            for pos in [(0, i), (i, last)] {
+                if walls[self.index(pos)] && previous[self.index(pos)].is_none() {
+                    previous[self.index(pos)] = Some(pos);
+                    queue.push_back(pos);
+                }
+            }
+        }
+        while let Some(pos) = queue.pop_front() {
+            if pos.1 == 0 || pos.0 == last {
+                let mut chain = vec![pos];
+                while let Some(before) = previous[self.index(*chain.last().unwrap())]
+                    && before != *chain.last().unwrap()
+                {
+                    chain.push(before);
+                }
+                return Some(Cut { byte, chain });
+            }
+            for dy in -1..=1 {
+                for dx in -1..=1 {
+                    let next = (pos.0.wrapping_add_signed(dy), pos.1.wrapping_add_signed(dx));
+                    if next.0 < self.size
+                        && next.1 < self.size
+                        && walls[self.index(next)]
+                        && previous[self.index(next)].is_none()
+                    {
+                        previous[self.index(next)] = Some(pos);
+                        queue.push_back(next);
+                    }
+                }
+            }
+        }
+        unreachable!("the exit is cut off, so there has to be a chain")
+    }
+
+    // the fallen bytes with the chain that cuts off the exit. positions are
+    // x first like in the input, so they get flipped around for drawing
+    fn frame(&self, cut: &Cut) -> Frame {
+        let mut frame = Frame::new(self.size, self.size);
+        for &(x, y) in self.bytes.iter().take(cut.byte + 1) {
+            frame.set((y, x), '#');
+        }
+        for &(x, y) in &cut.chain {
+            frame.set((y, x), '*');
+        }
+        let (x, y) = self.bytes[cut.byte];
+        frame.set((y, x), '@');
+        frame
     }
-    (bytes.collect(), area)
 }
+
 pub fn part1(input: String) -> String {
-    let (_, area) = parse_input(input);
-    find_shortest_path(&area, (0, 0), (70, 70))
+    Memory::parse(&input, SIZE)
+        .shortest_path(FALLEN)
         .expect("day 18 p1 error: no path found")
         .to_string()
 }
 
-fn index_is_tile_or_none(area: &[Vec<Tile>], y: usize, x: usize) -> bool {
-    get_2d(area, (y, x)).is_none_or(|tile| matches!(tile, Tile::Wall))
-}
-fn blocks_any_path(area: &[Vec<Tile>], y: usize, x: usize) -> bool {
-    if (index_is_tile_or_none(area, y, x.wrapping_sub(1)) && index_is_tile_or_none(area, y, x + 1))
-        || (index_is_tile_or_none(area, y.wrapping_sub(1), x)
-            && index_is_tile_or_none(area, y + 1, x))
-    {
-        return true;
-    }
-
-    false
-}
 pub fn part2(input: String) -> String {
-    let (remaining_bytes, mut area) = parse_input(input);
-    for byte in remaining_bytes {
-        area[byte.0][byte.1] = Tile::Wall;
-        // we can save time by only checking for path completeness when the new
-        // byte was surrounded by two blocks, which means that it blocked a way
-        // through. though even with this optimisation, this is rather slow
-        if blocks_any_path(&area, byte.0, byte.1) && !has_path(&area, (0, 0), (70, 70)) {
-            return format!("{},{}", byte.0, byte.1);
-        }
+    let memory = Memory::parse(&input, SIZE);
+    let Some(cut) = memory.cut() else {
+        return "-1".to_string();
+    };
+    if let Some(mut vis) = Visualizer::open("2024_day18_p2") {
+        vis.frame(&memory.frame(&cut));
     }
-    "-1".to_string()
+    let (x, y) = memory.bytes[cut.byte];
+    format!("{x},{y}")
 }
 
 #[cfg(test)]
 mod tests {
     use itertools::Itertools;
 
-    #[test]
-    fn sample_p1() {
-        use super::part1;
+    use super::Memory;
+    use crate::util::TestRng;
 
-        // sample input uses a smaller grid, so this is different from that even
-        // if it is the same input
-        let input = "5,4
+    const EXAMPLE: &str = "5,4
 4,2
 4,5
 3,0
@@ -146,8 +247,70 @@ mod tests {
 1,0
 0,5
 1,6
-2,0"
-        .to_string();
+2,0";
+
+    fn check_cut(memory: &Memory) {
+        let blocking = memory.first_blocking_union_find();
+        assert_eq!(blocking, memory.first_blocking_search());
+        let Some(cut) = memory.cut() else {
+            assert!(blocking.is_none());
+            return;
+        };
+        assert_eq!(Some(cut.byte), blocking);
+        assert!(memory.shortest_path(cut.byte).is_some());
+        assert!(memory.shortest_path(cut.byte + 1).is_none());
+
+        let fallen = &memory.bytes[..=cut.byte];
+        assert!(cut.chain.contains(&memory.bytes[cut.byte]));
+        assert!(cut.chain.iter().all(|byte| fallen.contains(byte)));
+        assert!(cut.chain.windows(2).all(|pair| {
+            pair[0] != pair[1]
+                && pair[0].0.abs_diff(pair[1].0) <= 1
+                && pair[0].1.abs_diff(pair[1].1) <= 1
+        }));
+        let last = memory.size - 1;
+        let (a, b) = (cut.chain[0], cut.chain[cut.chain.len() - 1]);
+        assert!(a.1 == 0 || a.0 == last);
+        assert!(b.0 == 0 || b.1 == last);
+    }
+
+    #[test]
+    fn example() {
+        let memory = Memory::parse(EXAMPLE, 7);
+        assert_eq!(memory.shortest_path(12), Some(22));
+        assert_eq!(memory.first_blocking_union_find(), Some(20));
+        assert_eq!(memory.bytes[20], (6, 1));
+        check_cut(&memory);
+
+        // the blocking byte is drawn at x 6, y 1
+        let frame = memory.frame(&memory.cut().unwrap()).to_ascii();
+        assert_eq!(frame.lines().nth(1).unwrap().chars().nth(6), Some('@'));
+        assert_eq!(frame.matches('@').count(), 1);
+    }
+
+    #[test]
+    fn random_bytes() {
+        let mut rng = TestRng::new(18);
+        let mut random = |below: usize| rng.below(below as u64) as usize;
+        for size in [1, 2, 5, 10, 20] {
+            // sometimes landing on the start or the exit, or twice on the
+            // same tile, and sometimes not enough to cut anything off
+            for count in [3, size * size / 4, size * size] {
+                let input = (0..count)
+                    .map(|_| format!("{},{}", random(size), random(size)))
+                    .join("\n");
+                check_cut(&Memory::parse(&input, size));
+            }
+        }
+    }
+
+    #[test]
+    fn sample_p1() {
+        use super::part1;
+
+        // sample input uses a smaller grid, so this is different from that even
+        // if it is the same input
+        let input = EXAMPLE.to_string();
         assert_eq!(part1(input), "146");
 
         let almost_block_second_row = (0..71)
@@ -1,89 +1,201 @@
-use itertools::Itertools;
-use regex::Regex;
 use rustc_hash::FxHashMap;
 
-// a homebrew and much slower version of the regex
-// fn can_make_design(original_design: &str, patterns: &Vec<&str>) -> bool {
-//     let mut to_test = vec![original_design];
-//     let mut seen = FxHashSet::default();
-//     while let Some(design) = to_test.pop() {
-//         // // avoid testing the same patterns again
-//         if seen.contains(design) {
-//             continue;
-//         }
-//         seen.insert(design);
-//         if design == "" {
-//             return true;
-//         }
-//         for pattern in patterns.iter() {
-//             if pattern.len() > design.len() {
-//                 break;
-//             }
-
-//             if design.starts_with(pattern) {
-//                 to_test.push(&design[pattern.len()..]);
-//             }
-//         }
-//     }
-//     false
-// }
+// all the towel patterns in a trie, so every towel that fits at some position
+// is found in one walk down from the root
+struct Towels<'a> {
+    patterns: Vec<&'a str>,
+    // node 0 is the root
+    children: Vec<FxHashMap<u8, usize>>,
+    // which pattern ends at a node, if any
+    ends: Vec<Option<usize>>,
+}
 
-pub fn part1(input: String) -> String {
-    let (patterns, designs) = input.split_once("\n\n").unwrap();
+// everything about making one design, from a single left to right pass
+struct Segmentation<'t, 'a> {
+    towels: &'t Towels<'a>,
+    // ways[i] = how many ways there are to make the first i stripes
+    ways: Vec<u128>,
+    // fewest[i] = (towel count, where the last towel starts, which pattern) for
+    // the way to make the first i stripes with the fewest towels
+    fewest: Vec<Option<(usize, usize, usize)>>,
+    // arriving[j] = (start, pattern) of every towel that fits ending at j,
+    // starting somewhere that can be made
+    arriving: Vec<Vec<(usize, usize)>>,
+}
 
-    let pattern_re_string = format!("^({})+$", patterns.split(", ").join("|"));
-    let pattern_re =
-        Regex::new(&pattern_re_string).expect("could not prepare regex. is the data malformed?");
+impl<'a> Towels<'a> {
+    fn new(patterns: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut towels = Towels {
+            patterns: Vec::new(),
+            children: vec![FxHashMap::default()],
+            ends: vec![None],
+        };
+        for pattern in patterns {
+            let mut node = 0;
+            for byte in pattern.bytes() {
+                node = match towels.children[node].get(&byte) {
+                    Some(&next) => next,
+                    None => {
+                        towels.children.push(FxHashMap::default());
+                        towels.ends.push(None);
+                        let next = towels.children.len() - 1;
+                        towels.children[node].insert(byte, next);
+                        next
+                    }
+                };
+            }
+            towels.ends[node] = Some(towels.patterns.len());
+            towels.patterns.push(pattern);
+        }
+        towels
+    }
 
-    designs
-        .lines()
-        .filter(|design| pattern_re.is_match(design))
-        .count()
-        .to_string()
-}
+    fn segment<'t>(&'t self, design: &str) -> Segmentation<'t, 'a> {
+        let design = design.as_bytes();
+        let n = design.len();
+        let mut ways = vec![0u128; n + 1];
+        let mut fewest = vec![None; n + 1];
+        let mut arriving = vec![Vec::new(); n + 1];
+        ways[0] = 1;
+        fewest[0] = Some((0, 0, 0));
+
+        for start in 0..n {
+            if ways[start] == 0 {
+                continue;
+            }
+            let towel_count = fewest[start].unwrap().0 + 1;
+            // walk down the trie for as long as the design matches
+            let mut node = 0;
+            for (end, byte) in design.iter().enumerate().skip(start) {
+                let Some(&next) = self.children[node].get(byte) else {
+                    break;
+                };
+                node = next;
+                if let Some(pattern) = self.ends[node] {
+                    let end = end + 1;
+                    ways[end] += ways[start];
+                    if fewest[end].is_none_or(|(count, _, _)| towel_count < count) {
+                        fewest[end] = Some((towel_count, start, pattern));
+                    }
+                    arriving[end].push((start, pattern));
+                }
+            }
+        }
 
-fn try_p2<'a>(design: &'a str, patterns: &Vec<&str>, cache: &mut FxHashMap<&'a str, u64>) -> u64 {
-    // memoization
-    if let Some(&res) = cache.get(&design) {
-        return res;
+        Segmentation {
+            towels: self,
+            ways,
+            fewest,
+            arriving,
+        }
     }
-    let mut count = 0;
-    // base case
-    if design.is_empty() {
-        return 1;
+}
+
+impl<'a> Segmentation<'_, 'a> {
+    fn count(&self) -> u128 {
+        *self.ways.last().unwrap()
     }
 
-    for pattern in patterns {
-        // designs are sorted so we can break early when they get too long
-        if pattern.len() > design.len() {
-            break;
+    // the towels of the way with the fewest towels, or None if the design
+    // can't be made
+    fn shortest(&self) -> Option<Vec<&'a str>> {
+        let mut end = self.ways.len() - 1;
+        self.fewest[end]?;
+        let mut towels = Vec::new();
+        while end > 0 {
+            let (_, start, pattern) = self.fewest[end].unwrap();
+            towels.push(self.towels.patterns[pattern]);
+            end = start;
         }
-        // recurse with all matching patterns removed from the beginning
-        if let Some(stripped) = design.strip_prefix(pattern) {
-            count += try_p2(stripped, patterns, cache);
+        towels.reverse();
+        Some(towels)
+    }
+
+    // every way to make the design. this goes backwards over towels that start
+    // somewhere that can be made, so there are no dead ends. part 2 only wants
+    // the count, which for the real designs is far too many to list
+    #[allow(dead_code)]
+    fn all(&self) -> Vec<Vec<&'a str>> {
+        fn walk<'a>(
+            segmentation: &Segmentation<'_, 'a>,
+            end: usize,
+            towels: &mut Vec<&'a str>,
+            out: &mut Vec<Vec<&'a str>>,
+        ) {
+            if end == 0 {
+                out.push(towels.iter().rev().copied().collect());
+                return;
+            }
+            for &(start, pattern) in &segmentation.arriving[end] {
+                towels.push(segmentation.towels.patterns[pattern]);
+                walk(segmentation, start, towels, out);
+                towels.pop();
+            }
         }
+
+        let mut out = Vec::new();
+        walk(self, self.ways.len() - 1, &mut Vec::new(), &mut out);
+        out
     }
-    cache.insert(design, count);
-    count
 }
 
-pub fn part2(input: String) -> String {
+fn parse_input(input: &str) -> (Towels<'_>, impl Iterator<Item = &str>) {
     let (patterns, designs) = input.split_once("\n\n").unwrap();
+    (Towels::new(patterns.split(", ")), designs.lines())
+}
 
-    let mut patterns = patterns.split(", ").collect::<Vec<_>>();
-    patterns.sort_by_key(|s| s.len());
-
-    let mut cache = FxHashMap::default();
+pub fn part1(input: String) -> String {
+    let (towels, designs) = parse_input(&input);
+    designs
+        .filter(|design| towels.segment(design).shortest().is_some())
+        .count()
+        .to_string()
+}
 
+pub fn part2(input: String) -> String {
+    let (towels, designs) = parse_input(&input);
     designs
-        .lines()
-        .map(|design| try_p2(design, &patterns, &mut cache))
-        .sum::<u64>()
+        .map(|design| towels.segment(design).count())
+        .sum::<u128>()
         .to_string()
 }
 
 #[cfg(test)]
 mod tests {
+    use super::Towels;
+
+    #[test]
+    fn segmentations() {
+        let towels = Towels::new(["r", "wr", "b", "g", "bwu", "rb", "gb", "br"]);
+
+        let segmentation = towels.segment("rrbgbr");
+        assert_eq!(segmentation.count(), 6);
+        // r rb gb r does it with 4 towels too
+        assert_eq!(segmentation.shortest(), Some(vec!["r", "rb", "g", "br"]));
+        let mut all = segmentation.all();
+        all.sort();
+        assert_eq!(all.len(), 6);
+        assert_eq!(all[0], vec!["r", "r", "b", "g", "b", "r"]);
+        assert!(all.iter().all(|towels| towels.concat() == "rrbgbr"));
+
+        let segmentation = towels.segment("ubwu");
+        assert_eq!(segmentation.count(), 0);
+        assert_eq!(segmentation.shortest(), None);
+        assert!(segmentation.all().is_empty());
+    }
+
+    #[test]
+    fn huge_counts() {
+        // every split of 150 a's into pieces of 1 and 2 is a fibonacci number
+        // that's too big for a u64
+        let towels = Towels::new(["a", "aa"]);
+        let (mut a, mut b) = (1u128, 1u128);
+        for _ in 0..150 {
+            (a, b) = (b, a + b);
+        }
+        assert_eq!(towels.segment(&"a".repeat(150)).count(), a);
+    }
+
     #[test]
     fn sample_p1() {
         use super::part1;
@@ -1,207 +1,349 @@
-use itertools::Itertools;
+use std::{cmp::Reverse, collections::BinaryHeap};
+
 use rustc_hash::FxHashMap;
 
-fn numpad_pos(c: char) -> (i32, i32) {
-    // 7 8 9
-    // 4 5 6
-    // 1 2 3
-    //   0 A
-    match c {
-        '7' => (0, 0),
-        '8' => (0, 1),
-        '9' => (0, 2),
-        '4' => (1, 0),
-        '5' => (1, 1),
-        '6' => (1, 2),
-        '1' => (2, 0),
-        '2' => (2, 1),
-        '3' => (2, 2),
-        '0' => (3, 1),
-        'A' => (3, 2),
-        _ => unreachable!(),
-    }
+const NUMPAD: &str = "789
+456
+123
+ 0A";
+
+const DIRPAD: &str = " ^A
+<v>";
+
+// a keypad drawn as text. spaces are gaps without a button, which a robot arm
+// must never point at, so the arm can only ever be on a key
+struct Keypad {
+    keys: FxHashMap<char, (i64, i64)>,
 }
-fn dirpad_pos(c: char) -> (i32, i32) {
-    //   ^ A
-    // < v >
-    match c {
-        '^' => (0, 1),
-        'A' => (0, 2),
-        '<' => (1, 0),
-        'v' => (1, 1),
-        '>' => (1, 2),
-        _ => {
-            println!("{c}");
-            unreachable!()
+
+// costs[(from, to)] = (human presses, route) for an arm to go from one key to
+// the other and press it
+type Level = FxHashMap<(char, char), (u64, String)>;
+
+impl Keypad {
+    fn parse(layout: &str) -> Self {
+        let mut keypad = Keypad {
+            keys: FxHashMap::default(),
+        };
+        for (y, line) in layout.lines().enumerate() {
+            for (x, c) in line.chars().enumerate() {
+                if c != ' ' {
+                    assert!(
+                        keypad.keys.insert(c, (y as i64, x as i64)).is_none(),
+                        "key {c} is there twice"
+                    );
+                }
+            }
         }
+        keypad
     }
-}
-fn move_vertical(y: i32, y_target: i32, output: &mut String) {
-    if y == y_target {
-        return;
+
+    fn pos(&self, key: char) -> (i64, i64) {
+        *self
+            .keys
+            .get(&key)
+            .unwrap_or_else(|| panic!("there's no {key} key"))
     }
-    let count = y.abs_diff(y_target);
-    let c = if y < y_target { 'v' } else { '^' };
-    (0..count).for_each(|_| output.push(c));
-}
-fn move_horizontal(x: i32, x_target: i32, output: &mut String) {
-    if x == x_target {
-        return;
+
+    // the key the arm points at after one move, if there is one
+    fn neighbour(&self, key: char, press: char) -> Option<char> {
+        let (y, x) = self.pos(key);
+        let pos = match press {
+            '^' => (y - 1, x),
+            'v' => (y + 1, x),
+            '<' => (y, x - 1),
+            '>' => (y, x + 1),
+            _ => panic!("{press} doesn't move the arm"),
+        };
+        self.keys
+            .iter()
+            .find(|&(_, &p)| p == pos)
+            .map(|(&key, _)| key)
     }
-    let count = x.abs_diff(x_target);
-    let c = if x < x_target { '>' } else { '<' };
-    (0..count).for_each(|_| output.push(c));
-}
-fn dirpad_path_to(c: char, state: &mut (i32, i32), output: &mut String) {
-    // the order of movement we want to execute is left/up/down/right in order
-    // of preference [1] while also avoiding going over the forbidden spot,
-    // which doesn't have a button and would break our bots
-
-    // [1]: https://old.reddit.com/r/adventofcode/comments/1hjgyps/2024_day_21_part_2_i_got_greedyish/
-
-    let target = dirpad_pos(c);
-    // starting from (1, 0) => avoid going over forbidden spot
-    if state.1 == 0 {
-        move_horizontal(state.1, target.1, output);
-        move_vertical(state.0, target.0, output);
-    // going to (1, 0) => avoid going over forbidden spot
-    } else if target.1 == 0 {
-        move_vertical(state.0, target.0, output);
-        move_horizontal(state.1, target.1, output);
-        // going left
-    } else if target.1 < state.1 {
-        move_horizontal(state.1, target.1, output);
-        move_vertical(state.0, target.0, output);
-    } else {
-        move_vertical(state.0, target.0, output);
-        move_horizontal(state.1, target.1, output);
+
+    // the cheapest way to get from a key to every key it can reach and press
+    // it, when the arm is worked from the level below. what a move costs
+    // depends on where the arm below was left, so this is dijkstra over (key
+    // this arm is on, key last pressed below)
+    fn routes(&self, below: &Level, from: char) -> FxHashMap<char, (u64, String)> {
+        let mut dist = FxHashMap::default();
+        // state -> (previous state, what was pressed below to get here)
+        let mut prev = FxHashMap::default();
+        let mut queue = BinaryHeap::new();
+        dist.insert((from, 'A'), 0);
+        queue.push(Reverse((0, from, 'A')));
+        while let Some(Reverse((d, key, last))) = queue.pop() {
+            if d > dist[&(key, last)] {
+                continue;
+            }
+            for press in ['^', 'v', '<', '>'] {
+                let (Some(next), Some((cost, _))) =
+                    (self.neighbour(key, press), below.get(&(last, press)))
+                else {
+                    continue;
+                };
+                let state = (next, press);
+                if dist.get(&state).is_none_or(|&old| d + cost < old) {
+                    dist.insert(state, d + cost);
+                    prev.insert(state, ((key, last), press));
+                    queue.push(Reverse((d + cost, next, press)));
+                }
+            }
+        }
+
+        // wherever the arm ends up, the arm below still has to press A
+        let mut best = FxHashMap::default();
+        for (&(key, last), &d) in &dist {
+            let Some((press, _)) = below.get(&(last, 'A')) else {
+                continue;
+            };
+            let candidate = (d + press, last);
+            if best.get(&key).is_none_or(|&old| candidate < old) {
+                best.insert(key, candidate);
+            }
+        }
+        best.into_iter()
+            .map(|(key, (cost, last))| {
+                let mut route = vec!['A'];
+                let mut state = (key, last);
+                while let Some(&(before, press)) = prev.get(&state) {
+                    route.push(press);
+                    state = before;
+                }
+                (key, (cost, route.into_iter().rev().collect()))
+            })
+            .collect()
     }
+}
 
-    *state = target;
+// keypads[0] has the code typed on it, every keypad is worked by a robot
+// using the one after it, and the last one is pressed by a human. every arm
+// starts on A
+struct Chain {
+    keypads: Vec<Keypad>,
+    // costs[i] has the routes on keypad i
+    costs: Vec<Level>,
 }
-fn numpad_path_to(c: char, state: &mut (i32, i32), output: &mut String) {
-    // the order of movement we want to execute is left/up/down/right in order
-    // of preference [1] while also avoiding going over the forbidden spot,
-    // which doesn't have a button and would break our bots
-
-    // [1]: https://old.reddit.com/r/adventofcode/comments/1hjgyps/2024_day_21_part_2_i_got_greedyish/
-    let target = numpad_pos(c);
-
-    //starting from (_, 0) (left col) and going to (3, _) (bottom row) => avoid
-    // going over forbidden spot
-    if state.1 == 0 && target.0 == 3 {
-        move_horizontal(state.1, target.1, output);
-        move_vertical(state.0, target.0, output);
-    // starting from (3, _) (bottom row) and going to (_, 0) (left col) => avoid
-    // going over forbidden spot
-    } else if state.0 == 3 && target.1 == 0 {
-        move_vertical(state.0, target.0, output);
-        move_horizontal(state.1, target.1, output);
-    } else if target.1 < state.1 {
-        move_horizontal(state.1, target.1, output);
-        move_vertical(state.0, target.0, output);
-    } else {
-        move_vertical(state.0, target.0, output);
-        move_horizontal(state.1, target.1, output);
+
+impl Chain {
+    fn new(keypads: Vec<Keypad>) -> Self {
+        for keypad in &keypads[1..] {
+            assert!(
+                "<>^vA".chars().all(|key| keypad.keys.contains_key(&key)),
+                "robots have to be controlled with a directional keypad"
+            );
+        }
+
+        // the human just presses the key, so that's always 1. every other
+        // level goes with whichever route is cheapest for the level below.
+        // keys that can't be reached at all get left out
+        let mut costs: Vec<Level> = Vec::new();
+        for keypad in keypads.iter().rev() {
+            let mut level = FxHashMap::default();
+            for &from in keypad.keys.keys() {
+                match costs.last() {
+                    None => {
+                        for &to in keypad.keys.keys() {
+                            level.insert((from, to), (1, to.to_string()));
+                        }
+                    }
+                    Some(below) => {
+                        for (to, best) in keypad.routes(below, from) {
+                            level.insert((from, to), best);
+                        }
+                    }
+                }
+            }
+            costs.push(level);
+        }
+        costs.reverse();
+        Chain { keypads, costs }
     }
-    *state = target;
-}
-fn numpad_moves(code: &str) -> String {
-    let mut output = String::new();
-    // robot starts on the button 'A'
-    let mut state = numpad_pos('A');
-    for number in code.chars() {
-        // for each part of the code (including the 'A'), we navigate the
-        // robotic finger to that button, and press it (A)
-        numpad_path_to(number, &mut state, &mut output);
-        output.push('A');
+
+    // a code typed on the first keypad, then the robot inputs below it, ending
+    // with what the human presses
+    fn presses(&self, code: &str) -> u64 {
+        cost(&self.costs[0], code)
     }
-    output
-}
 
-fn calculate_button_press_count(numpad_result: String, iterations_after_first: u32) -> usize {
-    // robot starts on the button 'A'
-    let chars = ['<', '>', '^', 'v', 'A'];
-    // pre-calculate amount of button presses needed for all moves (i.e. from
-    // button '>' to '<')
-    let mut count_map = chars
-        .iter()
-        .permutations(2)
-        .map(|vec| {
-            let mut output = String::new();
-            dirpad_path_to(*vec[1], &mut dirpad_pos(*vec[0]), &mut output);
-            output.push('A');
-            ((*vec[0], *vec[1]), output.len())
-        })
-        .collect::<FxHashMap<_, _>>();
-
-    // sum the cost of a set of movements on a direction pad, starting from 'A'
-    let sum_path = |moves: String, count_map: &FxHashMap<(char, char), usize>| {
-        // intermediate bots always start and end on the 'A' button
-        let mut prev = 'A';
-        let mut n = 0;
-        for c in moves.chars() {
-            n += if prev == c {
-                // moving to the same button would simply indicate another 'A'
-                // press when the bots are lined up
-                1
-            } else {
-                *count_map.get(&(prev, c)).unwrap()
-            };
-            prev = c;
+    // what gets pressed on every keypad, starting with the code itself. the
+    // answers only need the count, this is for seeing what the robots do
+    #[allow(dead_code)]
+    fn sequences(&self, code: &str) -> Vec<String> {
+        let mut sequences = vec![code.to_string()];
+        for level in &self.costs[..self.keypads.len() - 1] {
+            let above = sequences.last().unwrap();
+            let below = transitions(above)
+                .map(|pair| step(level, pair).1.as_str())
+                .collect();
+            sequences.push(below);
         }
-        n
-    };
-
-    let mut count_map2 = FxHashMap::default();
-    for _ in 0..iterations_after_first {
-        chars.iter().permutations(2).for_each(|vec| {
-            let mut output = String::new();
-            // get base route to other to get characters to iterate over
-            dirpad_path_to(*vec[1], &mut dirpad_pos(*vec[0]), &mut output);
-            output.push('A');
-
-            let n = sum_path(output, &count_map);
-            count_map2.insert((*vec[0], *vec[1]), n);
-        });
-
-        std::mem::swap(&mut count_map, &mut count_map2);
-        count_map2.clear();
+        sequences
     }
+}
 
-    // finally, we apply the calculated move costs to the original string
-    sum_path(numpad_result, &count_map)
+// every key press and the key the arm was on before it, starting from A
+fn transitions(presses: &str) -> impl Iterator<Item = (char, char)> + '_ {
+    "A".chars().chain(presses.chars()).zip(presses.chars())
 }
 
-fn calculate_complexity(code: &str, robot_count: u32) -> usize {
-    // first we get an actual set of moves (like "<A^A>^^AvvvA")
-    let numpad_result = numpad_moves(code);
-    // and then we calculate how many moves we would need to perform based on
-    // the count of the intermediate robots
-    let intermediate_costs = calculate_button_press_count(numpad_result, robot_count - 1);
-    code.strip_suffix('A').unwrap().parse::<usize>().unwrap() * intermediate_costs
+fn step(level: &Level, (from, to): (char, char)) -> &(u64, String) {
+    level
+        .get(&(from, to))
+        .unwrap_or_else(|| panic!("can't get from {from} to {to}"))
 }
 
-pub fn part1(input: String) -> String {
-    let codes = input.lines();
+fn cost(level: &Level, presses: &str) -> u64 {
+    transitions(presses).map(|pair| step(level, pair).0).sum()
+}
 
-    codes
-        .map(|code| calculate_complexity(code, 2))
-        .sum::<usize>()
+fn chain(robots: usize) -> Chain {
+    // one directional keypad per robot, and one for the human
+    let mut keypads = vec![Keypad::parse(NUMPAD)];
+    keypads.extend((0..=robots).map(|_| Keypad::parse(DIRPAD)));
+    Chain::new(keypads)
+}
+
+fn complexity(chain: &Chain, code: &str) -> u64 {
+    code.strip_suffix('A').unwrap().parse::<u64>().unwrap() * chain.presses(code)
+}
+
+pub fn part1(input: String) -> String {
+    let chain = chain(2);
+    input
+        .lines()
+        .map(|code| complexity(&chain, code))
+        .sum::<u64>()
         .to_string()
 }
 
 pub fn part2(input: String) -> String {
-    let codes = input.lines();
-
-    codes
-        .map(|code| calculate_complexity(code, 25))
-        .sum::<usize>()
+    let chain = chain(25);
+    input
+        .lines()
+        .map(|code| complexity(&chain, code))
+        .sum::<u64>()
         .to_string()
 }
 
 #[cfg(test)]
 mod tests {
+    use std::collections::VecDeque;
+
+    use rustc_hash::FxHashSet;
+
+    use super::{Chain, DIRPAD, Keypad, chain};
+
+    // presses the buttons of the last keypad and sees what comes out of the
+    // first one. None if an arm points at a gap
+    fn simulate(chain: &Chain, presses: &str) -> Option<String> {
+        let mut output = presses.to_string();
+        for keypad in chain.keypads[..chain.keypads.len() - 1].iter().rev() {
+            let mut arm = 'A';
+            let mut typed = String::new();
+            for press in output.chars() {
+                match press {
+                    'A' => typed.push(arm),
+                    _ => arm = keypad.neighbour(arm, press)?,
+                }
+            }
+            output = typed;
+        }
+        Some(output)
+    }
+
+    // what a human press does to the arms. Some(Some(key)) if it types a key
+    // on the first keypad, None if an arm ends up over a gap
+    fn press(chain: &Chain, arms: &mut [char], human: char) -> Option<Option<char>> {
+        let mut press = human;
+        for level in (0..arms.len()).rev() {
+            if press != 'A' {
+                arms[level] = chain.keypads[level].neighbour(arms[level], press)?;
+                return Some(None);
+            }
+            // A passes the key the arm is on up to the next keypad
+            press = arms[level];
+        }
+        Some(Some(press))
+    }
+
+    // bfs over every arm at once, with the human pressing one key at a time
+    fn brute_force(chain: &Chain, code: &str) -> Option<u64> {
+        let code = code.chars().collect::<Vec<_>>();
+        let robots = chain.keypads.len() - 1;
+        let start = (vec!['A'; robots], 0);
+        let mut seen = FxHashSet::from_iter([start.clone()]);
+        let mut queue = VecDeque::from([(start, 0)]);
+        while let Some(((arms, typed), presses)) = queue.pop_front() {
+            if typed == code.len() {
+                return Some(presses);
+            }
+            for human in ['^', 'v', '<', '>', 'A'] {
+                let mut arms = arms.clone();
+                let mut typed = typed;
+                match press(chain, &mut arms, human) {
+                    Some(Some(key)) if key == code[typed] => typed += 1,
+                    Some(None) => {}
+                    // the wrong key, or an arm over a gap
+                    _ => continue,
+                }
+                if seen.insert((arms.clone(), typed)) {
+                    queue.push_back(((arms, typed), presses + 1));
+                }
+            }
+        }
+        None
+    }
+
+    #[test]
+    fn sequences() {
+        let chain = chain(2);
+        let sequences = chain.sequences("029A");
+        assert_eq!(sequences.len(), 4);
+        assert_eq!(sequences[3].len(), 68);
+        assert_eq!(sequences[3].len() as u64, chain.presses("029A"));
+        assert_eq!(simulate(&chain, &sequences[3]).as_deref(), Some("029A"));
+        for code in ["980A", "179A", "456A", "379A"] {
+            let sequences = chain.sequences(code);
+            assert_eq!(
+                simulate(&chain, sequences.last().unwrap()).as_deref(),
+                Some(code)
+            );
+        }
+    }
+
+    #[test]
+    fn custom_keypads() {
+        // a keypad with a hole in the middle, so going straight across means
+        // going around it
+        let ring = Keypad::parse(
+            "123
+4 5
+67A",
+        );
+        let chain = Chain::new(vec![ring, Keypad::parse(DIRPAD), Keypad::parse(DIRPAD)]);
+        // straight across the hole has to go around it
+        for code in ["1A", "5A", "71A", "4A", "45A", "72A", "2754A"] {
+            let sequences = chain.sequences(code);
+            let presses = sequences.last().unwrap();
+            assert_eq!(presses.len() as u64, chain.presses(code));
+            assert_eq!(simulate(&chain, presses).as_deref(), Some(code));
+            assert_eq!(brute_force(&chain, code), Some(chain.presses(code)));
+        }
+        assert!(chain.costs[0].contains_key(&('7', '2')));
+        assert!(chain.costs[0].contains_key(&('4', '5')));
+
+        // the real keypads come out optimal too
+        let real = super::chain(2);
+        for code in ["029A", "980A", "456A"] {
+            assert_eq!(brute_force(&real, code), Some(real.presses(code)));
+        }
+
+        // typing straight on the human's keypad is one press per key
+        let chain = Chain::new(vec![Keypad::parse(DIRPAD)]);
+        assert_eq!(chain.presses("<<^A"), 4);
+    }
+
     #[test]
     fn sample_p1() {
         use super::part1;
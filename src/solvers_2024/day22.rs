@@ -2,51 +2,121 @@ use std::array;
 
 use bitvec::bitvec;
 
-fn process(mut monkey: u64) -> u64 {
-    monkey = ((64 * monkey) ^ monkey) % 16777216;
+// secrets are 24 bits
+const MASK: u64 = (1 << 24) - 1;
+
+// every step is shifts and xors, so it's linear over GF(2)^24, which is what
+// makes jumping ahead work
+fn next(mut secret: u64) -> u64 {
+    secret = ((secret << 6) ^ secret) & MASK;
+    secret = (secret >> 5) ^ secret;
+    ((secret << 11) ^ secret) & MASK
+}
 
-    monkey = (monkey ^ (monkey / 32)) % 16777216;
+// the secrets a monkey goes through after the initial one
+struct SecretStream {
+    secret: u64,
+}
 
-    monkey = ((monkey * 2048) ^ monkey) % 16777216;
+impl SecretStream {
+    fn new(secret: u64) -> Self {
+        SecretStream { secret }
+    }
+}
 
-    monkey
+impl Iterator for SecretStream {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        self.secret = next(self.secret);
+        Some(self.secret)
+    }
 }
 
-pub fn part1(input: String) -> String {
-    let monkeys = input
-        .lines()
-        .map(|line| line.parse::<u64>().unwrap())
-        .collect::<Vec<_>>();
+// a number of steps as a 24x24 matrix over GF(2). columns[j] is where bit j
+// ends up, so applying it is xoring the columns of the bits that are set
+#[derive(Clone, Copy)]
+struct Jump {
+    columns: [u32; 24],
+}
+
+impl Jump {
+    fn identity() -> Self {
+        Jump {
+            columns: array::from_fn(|j| 1 << j),
+        }
+    }
 
-    let mut total = 0;
-    const LANES: usize = 16;
-    for monkes in monkeys.chunks_exact(LANES) {
-        let mut monkes: [u64; LANES] = array::from_fn(|i| monkes[i]);
-        for _ in 0..2000 {
-            monkes = monkes
-                .map(|monkey| ((64 * monkey) ^ monkey) % 16777216)
-                .map(|monkey| (monkey ^ (monkey / 32)) % 16777216)
-                .map(|monkey| ((monkey * 2048) ^ monkey) % 16777216);
+    // square and multiply, so it's O(log steps)
+    fn new(mut steps: u128) -> Self {
+        let mut power = Jump {
+            columns: array::from_fn(|j| next(1 << j) as u32),
+        };
+        let mut jump = Jump::identity();
+        while steps > 0 {
+            if steps & 1 == 1 {
+                jump = jump.then(&power);
+            }
+            power = power.then(&power);
+            steps >>= 1;
         }
-        for monkey in monkes {
-            total += monkey;
+        jump
+    }
+
+    fn apply(&self, secret: u64) -> u64 {
+        let mut out = 0;
+        for (j, column) in self.columns.iter().enumerate() {
+            if secret >> j & 1 == 1 {
+                out ^= column;
+            }
         }
+        out as u64
     }
-    for monkey in monkeys.chunks_exact(LANES).remainder() {
-        let mut monkey = *monkey;
-        for _ in 0..2000 {
-            monkey = process(monkey);
+
+    // the same thing on a batch of secrets at once. masks instead of branches
+    // so the compiler can turn it into simd
+    fn apply_lanes<const LANES: usize>(&self, lanes: [u64; LANES]) -> [u64; LANES] {
+        let mut out = [0; LANES];
+        for (j, &column) in self.columns.iter().enumerate() {
+            for (out, secret) in out.iter_mut().zip(lanes) {
+                *out ^= u64::from(column) & (secret >> j & 1).wrapping_neg();
+            }
+        }
+        out
+    }
+
+    // the leftovers that don't fill up a batch go one at a time
+    fn apply_all(&self, secrets: &[u64]) -> Vec<u64> {
+        const LANES: usize = 16;
+        let chunks = secrets.chunks_exact(LANES);
+        let remainder = chunks.remainder();
+        let mut out = Vec::with_capacity(secrets.len());
+        for chunk in chunks {
+            out.extend(self.apply_lanes::<LANES>(array::from_fn(|i| chunk[i])));
+        }
+        out.extend(remainder.iter().map(|&secret| self.apply(secret)));
+        out
+    }
+
+    // this jump followed by the other one
+    fn then(&self, other: &Jump) -> Jump {
+        Jump {
+            columns: self.columns.map(|column| other.apply(column as u64) as u32),
         }
-        total += monkey;
     }
-    total.to_string()
 }
 
-fn index(deltas: (i8, i8, i8, i8)) -> usize {
-    (deltas.0 + 9) as usize * 19usize.pow(3)
-        + (deltas.1 + 9) as usize * 19usize.pow(2)
-        + (deltas.2 + 9) as usize * 19usize
-        + (deltas.3 + 9) as usize
+pub fn part1(input: String) -> String {
+    let monkeys = input
+        .lines()
+        .map(|line| line.parse::<u64>().unwrap())
+        .collect::<Vec<_>>();
+
+    Jump::new(2000)
+        .apply_all(&monkeys)
+        .iter()
+        .sum::<u64>()
+        .to_string()
 }
 
 pub fn part2(input: String) -> String {
@@ -56,35 +126,28 @@ pub fn part2(input: String) -> String {
     // we use vectors with indexes based on the delta sequenes, as while
     // there are a lot of possible combinations, the total amount
     // is still relatively low and clearly outperforms fxhashmap
-    let mut map = vec![0; 19usize.pow(4)];
-    for mut monkey in monkeys {
+    const SEQUENCES: usize = 19usize.pow(4);
+    let mut map = vec![0; SEQUENCES];
+    for monkey in monkeys {
         // we want to avoid checking delta sequences multiple times as the
         // monkey buys the first one that matches
-        let mut seen = bitvec![0; 19usize.pow(4)];
+        let mut seen = bitvec![0; SEQUENCES];
         let mut old_price = (monkey % 10) as i8;
-        let mut deltas = vec![];
-
-        for _ in 0..2000 {
-            monkey = process(monkey);
-            let price = (monkey % 10) as i8;
+        // the last 4 deltas as base 19 digits, the oldest one falls off the
+        // top as new ones come in
+        let mut idx = 0;
 
+        for (i, secret) in SecretStream::new(monkey).take(2000).enumerate() {
+            let price = (secret % 10) as i8;
             let delta = price - old_price;
-            deltas.push(delta);
-
             old_price = price;
 
-            let n = deltas.len();
-            if n < 4 {
-                continue;
-            }
-            let deltas = (deltas[n - 4], deltas[n - 3], deltas[n - 2], deltas[n - 1]);
-
-            let idx = index(deltas);
-            if seen[idx] {
+            idx = (idx * 19 + (delta + 9) as usize) % SEQUENCES;
+            if i < 3 || seen[idx] {
                 continue;
             }
             seen.set(idx, true);
-            map[index(deltas)] += i16::from(price);
+            map[idx] += i16::from(price);
         }
     }
 
@@ -93,6 +156,57 @@ pub fn part2(input: String) -> String {
 
 #[cfg(test)]
 mod tests {
+    use super::{Jump, SecretStream};
+
+    #[test]
+    fn stream() {
+        let secrets = SecretStream::new(123).take(10).collect::<Vec<_>>();
+        assert_eq!(
+            secrets,
+            vec![
+                15887950, 16495136, 527345, 704524, 1553684, 12683156, 11100544, 12249484, 7753432,
+                5908254
+            ]
+        );
+    }
+
+    #[test]
+    fn lanes_match_scalar() {
+        // 16 lanes and a remainder of 5
+        let secrets = (0..21)
+            .map(|i| i * 1_000_003 % (1 << 24))
+            .collect::<Vec<_>>();
+        let scalar = secrets
+            .iter()
+            .map(|&secret| SecretStream::new(secret).nth(99).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(Jump::new(100).apply_all(&secrets), scalar);
+        assert_eq!(Jump::new(2000).apply_lanes([1, 10]), [8685429, 4700978]);
+        assert_eq!(Jump::new(0).apply_all(&[5]), vec![5]);
+    }
+
+    #[test]
+    fn jump_ahead() {
+        for steps in [0, 1, 2, 7, 2000, 12345] {
+            let jump = Jump::new(steps);
+            for secret in [1, 100, 2024, 0xabcdef] {
+                let stepped = SecretStream::new(secret)
+                    .take(steps as usize)
+                    .last()
+                    .unwrap_or(secret);
+                assert_eq!(jump.apply(secret), stepped, "{steps} steps from {secret}");
+            }
+        }
+
+        // huge jumps split up any way land in the same place
+        let (a, b) = (1u128 << 100, 987654321987654321u128);
+        assert_eq!(
+            Jump::new(a + b).apply(2024),
+            Jump::new(b).apply(Jump::new(a).apply(2024))
+        );
+        assert_ne!(Jump::new(a).apply(2024), 2024);
+    }
+
     #[test]
     fn sample_p1() {
         use super::part1;
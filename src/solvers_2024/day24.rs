@@ -1,5 +1,6 @@
 use regex::Regex;
-use rustc_hash::FxHashMap;
+
+use crate::util::Interner;
 
 fn execute_op(lhs: u8, rhs: u8, op: &str) -> u8 {
     match op {
@@ -23,21 +24,29 @@ fn parse_input(input: &str) -> (&str, Vec<[&str; 4]>) {
     (wire_values, gate_connections)
 }
 pub fn part1(input: String) -> String {
-    let (wire_values, mut gate_connections) = parse_input(&input);
-    let mut wire_map = FxHashMap::default();
+    let (wire_values, gate_connections) = parse_input(&input);
+    let mut wires = Interner::new();
+    let mut gates = gate_connections
+        .iter()
+        .map(|&[lhs, op, rhs, ret]| (wires.intern(lhs), op, wires.intern(rhs), wires.intern(ret)))
+        .collect::<Vec<_>>();
+    // indexed by wire id, None until the gate feeding it has been applied
+    let mut values = vec![None; wires.len()];
     wire_values.lines().for_each(|line| {
         let (name, value) = line.split_once(": ").unwrap();
-        wire_map.insert(name, value.parse::<u8>().unwrap());
+        let wire = wires.intern(name) as usize;
+        values.resize(wires.len(), None);
+        values[wire] = Some(value.parse::<u8>().unwrap());
     });
 
     // loop while removing connections until they have all been applied
-    while !gate_connections.is_empty() {
-        gate_connections.retain(|&[lhs, op, rhs, ret]| {
-            let lhs_val = wire_map.get(lhs);
-            let rhs_val = wire_map.get(rhs);
+    while !gates.is_empty() {
+        gates.retain(|&(lhs, op, rhs, ret)| {
+            let lhs_val = values[lhs as usize];
+            let rhs_val = values[rhs as usize];
             match (lhs_val, rhs_val) {
-                (Some(&a), Some(&b)) => {
-                    wire_map.insert(ret, execute_op(a, b, op));
+                (Some(a), Some(b)) => {
+                    values[ret as usize] = Some(execute_op(a, b, op));
                     false
                 }
                 _ => true,
@@ -47,9 +56,8 @@ pub fn part1(input: String) -> String {
 
     let mut out = 0u64;
     for i in 0..64 {
-        let s = format!("z{i:02}");
-        if let Some(&val) = wire_map.get(&s.as_str()) {
-            out |= u64::from(val) << i;
+        if let Some(wire) = wires.get(&format!("z{i:02}")) {
+            out |= u64::from(values[wire as usize].unwrap()) << i;
         } else {
             break;
         }
@@ -60,20 +68,25 @@ pub fn part1(input: String) -> String {
 
 pub fn part2(input: String) -> String {
     let (_, gate_connections) = parse_input(&input);
-    let mut wire_map: FxHashMap<&str, Vec<(&str, &str)>> = FxHashMap::default();
-
-    // we need a map to know what operations follow another operation
-    for &[lhs, op, rhs, ret] in &gate_connections {
-        wire_map.entry(lhs).or_default().push((op, ret));
-        wire_map.entry(rhs).or_default().push((op, ret));
+    let mut wires = Interner::new();
+    let mut followers: Vec<Vec<&str>> = Vec::new();
+
+    // we need to know what operations follow another operation, indexed by
+    // wire id
+    for &[lhs, op, rhs, _] in &gate_connections {
+        for input in [lhs, rhs] {
+            let wire = wires.intern(input) as usize;
+            followers.resize(wires.len(), Vec::new());
+            followers[wire].push(op);
+        }
     }
 
     let mut wrong_outputs = vec![];
     for &[lhs, op, rhs, ret] in &gate_connections {
         // basically we ensure the adder looks like this:
         // https://en.wikipedia.org/wiki/Adder_(electronics)#/media/File:Fulladder.gif
-        let chained_ops = wire_map.get(&ret);
-        let chained_ops_contain = |op| chained_ops.is_some_and(|v| v.iter().any(|a| a.0 == op));
+        let chained_ops = wires.get(ret).map(|wire| &followers[wire as usize]);
+        let chained_ops_contain = |op| chained_ops.is_some_and(|v| v.contains(&op));
 
         let has_chained_xor = chained_ops_contain("XOR");
         let has_chained_and = chained_ops_contain("AND");
@@ -1,84 +1,153 @@
-pub fn part1(input: String) -> String {
-    let mut dial = 50;
-    let mut password = 0;
-    for rotation in input.lines() {
-        let (direction, steps) = rotation.split_at(1);
+#[derive(Debug, Clone, Copy)]
+enum Rotation {
+    Left(u64),
+    Right(u64),
+}
+
+impl Rotation {
+    fn parse(line: &str) -> Self {
+        let (direction, steps) = line.split_at(1);
         let steps = steps
-            .parse::<i16>()
+            .parse()
             .expect("one of the input lines could not be parsed");
-
         match direction {
-            "L" => {
-                dial -= steps;
-            }
-            "R" => {
-                dial += steps;
-            }
+            "L" => Rotation::Left(steps),
+            "R" => Rotation::Right(steps),
             _ => unreachable!(),
         }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Dial {
+    size: u64,
+    position: u64,
+}
+
+impl Dial {
+    fn new(size: u64, start: u64) -> Self {
+        assert!(
+            start < size,
+            "dial starts at {start} but only has {size} positions"
+        );
+        Dial {
+            size,
+            position: start,
+        }
+    }
 
-        dial %= 100;
-        if dial == 0 {
-            password += 1;
+    // how many of the rotation's clicks leave the dial pointing at marker,
+    // including the last one. the position it starts on doesn't count
+    fn passes(&self, rotation: Rotation, marker: u64) -> u64 {
+        // u128 so big dials can't overflow
+        let (size, position, marker) = (self.size as u128, self.position as u128, marker as u128);
+        // clicks until the first time we hit the marker
+        let (steps, first) = match rotation {
+            Rotation::Left(steps) => (steps as u128, (position + size - marker) % size),
+            Rotation::Right(steps) => (steps as u128, (marker + size - position) % size),
+        };
+        let first = if first == 0 { size } else { first };
+        if steps < first {
+            0
+        } else {
+            ((steps - first) / size + 1) as u64
         }
     }
 
-    password.to_string()
+    fn rotate(&mut self, rotation: Rotation) {
+        let (size, position) = (self.size as u128, self.position as u128);
+        let position = match rotation {
+            Rotation::Left(steps) => position + size - steps as u128 % size,
+            Rotation::Right(steps) => position + steps as u128,
+        };
+        self.position = (position % size) as u64;
+    }
 }
 
-pub fn part2(input: String) -> String {
-    let mut dial = 50;
-    let mut password = 0;
-    for rotation in input.lines() {
-        let (direction, steps) = rotation.split_at(1);
-        let mut steps = steps
-            .parse::<i16>()
-            .expect("one of the input lines could not be parsed");
-
-        let hundreds = steps / 100;
-        password += hundreds;
-        steps %= 100;
+pub fn part1(input: String) -> String {
+    let mut dial = Dial::new(100, 50);
+    input
+        .lines()
+        .filter(|line| {
+            dial.rotate(Rotation::parse(line));
+            dial.position == 0
+        })
+        .count()
+        .to_string()
+}
 
-        let dial_start = dial;
+pub fn part2(input: String) -> String {
+    let mut dial = Dial::new(100, 50);
+    input
+        .lines()
+        .map(|line| {
+            let rotation = Rotation::parse(line);
+            let passes = dial.passes(rotation, 0);
+            dial.rotate(rotation);
+            passes
+        })
+        .sum::<u64>()
+        .to_string()
+}
 
-        match direction {
-            "L" => {
-                dial -= steps;
-            }
-            "R" => {
-                dial += steps;
+#[cfg(test)]
+mod tests {
+    use super::{Dial, Rotation};
+    use crate::util::TestRng;
+
+    // turns the dial one click at a time
+    fn brute_force(dial: &mut Dial, rotation: Rotation, marker: u64) -> u64 {
+        let (steps, click) = match rotation {
+            Rotation::Left(steps) => (steps, dial.size - 1),
+            Rotation::Right(steps) => (steps, 1),
+        };
+        let mut passes = 0;
+        for _ in 0..steps {
+            dial.position = (dial.position + click) % dial.size;
+            if dial.position == marker {
+                passes += 1;
             }
-            _ => unreachable!(),
         }
+        passes
+    }
 
-        match dial {
-            ..0 => {
-                dial += 100;
-                // if we e.g. go 5 clicks left from 0 we didn't actually cross
-                // zero because we started from it, and shouldn't get a password
-                // count
-                if dial_start != 0 {
-                    password += 1;
-                }
-            }
-            0 => {
-                password += 1;
-            }
-            100.. => {
-                dial -= 100;
-                if dial_start != 0 {
-                    password += 1;
-                }
+    #[test]
+    fn matches_brute_force() {
+        let mut rng = TestRng::new(12345);
+        let mut random = |below: u64| rng.below(below);
+        for _ in 0..200 {
+            let size = random(20) + 1;
+            let mut dial = Dial::new(size, random(size));
+            let mut reference = dial;
+            let marker = random(size);
+            for _ in 0..20 {
+                let steps = random(100);
+                let rotation = if random(2) == 0 {
+                    Rotation::Left(steps)
+                } else {
+                    Rotation::Right(steps)
+                };
+                let passes = dial.passes(rotation, marker);
+                dial.rotate(rotation);
+                assert_eq!(passes, brute_force(&mut reference, rotation, marker));
+                assert_eq!(dial.position, reference.position);
             }
-            _ => {}
         }
     }
 
-    password.to_string()
-}
+    #[test]
+    fn huge_rotations() {
+        let dial = Dial::new(100, 50);
+        assert_eq!(dial.passes(Rotation::Right(u64::MAX), 0), u64::MAX / 100);
+        let mut dial = Dial::new(u64::MAX, u64::MAX - 1);
+        assert_eq!(dial.passes(Rotation::Right(u64::MAX), 3), 1);
+        assert_eq!(dial.passes(Rotation::Left(u64::MAX), u64::MAX - 1), 1);
+        dial.rotate(Rotation::Right(u64::MAX - 1));
+        assert_eq!(dial.position, u64::MAX - 2);
+        dial.rotate(Rotation::Left(u64::MAX));
+        assert_eq!(dial.position, u64::MAX - 2);
+    }
 
-#[cfg(test)]
-mod tests {
     #[test]
     fn sample_p1() {
         use super::part1;
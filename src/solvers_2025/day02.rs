@@ -1,48 +1,170 @@
+use std::ops::RangeInclusive;
+
 use regex::Regex;
 
-fn parse_input(input: String) -> Vec<(u64, u64)> {
+use crate::util::RangeSet;
+
+fn parse_input(input: String) -> RangeSet {
     let re = Regex::new(r"(\d+)-(\d+)").unwrap();
     re.captures_iter(&input)
         .map(|m| {
             let (_, [start, end]) = m.extract();
-            (start.parse::<u64>().unwrap(), end.parse::<u64>().unwrap())
+            start.parse::<u64>().unwrap()..=end.parse::<u64>().unwrap()
         })
         .collect()
 }
 
-fn solve(ranges: &[(u64, u64)], max_repeats: u32) -> u64 {
-    let mut ids = Vec::new();
+// count and sum of some ids, kept together so inclusion-exclusion can add and
+// subtract both at once. the sum is None once it stops fitting in a u128
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Tally {
+    count: u128,
+    sum: Option<u128>,
+}
 
-    // idea: instead of looping through the range, we loop through its digit
-    // count and small numbers (repeaters) which we concatenate using powers of
-    // 10 to test if the repeater might be in the range
-    for &(start, end) in ranges {
-        for digit_count in (start.ilog10() + 1)..=(end.ilog10() + 1) {
-            for repeats in 2..=digit_count.min(max_repeats) {
-                if !digit_count.is_multiple_of(repeats) {
-                    continue;
-                }
-                let repeater_size = digit_count / repeats;
-
-                // e.g. for repeater size 2 we loop from 10 to 99
-                for x in 10u64.pow(repeater_size - 1)..=(10u64.pow(repeater_size) - 1) {
-                    let mut number = 0;
-                    // e.g.
-                    // 11885 + 11885*10^5 + 11885*10^10
-                    // = 118851188511885
-                    for repeat in 0..(repeats) {
-                        number += x * 10u64.pow(repeater_size * repeat)
-                    }
-                    if number >= start && number <= end {
-                        ids.push(number);
-                    }
+impl Default for Tally {
+    fn default() -> Self {
+        Tally {
+            count: 0,
+            sum: Some(0),
+        }
+    }
+}
+
+impl Tally {
+    fn add(self, other: Tally) -> Tally {
+        Tally {
+            count: self.count + other.count,
+            sum: self.sum.zip(other.sum).and_then(|(a, b)| a.checked_add(b)),
+        }
+    }
+
+    fn sub(self, other: Tally) -> Tally {
+        Tally {
+            count: self.count - other.count,
+            sum: self.sum.zip(other.sum).map(|(a, b)| a - b),
+        }
+    }
+}
+
+// a number with `digits` digits that's a block repeated `repeats` times is
+// block * multiplier, where the multiplier is 1 + base^len + base^(2 len) + ...
+// (like 1001 or 10101 in base 10). returns the multiplier and the blocks that
+// land inside the range, or None if none do
+fn blocks(
+    range: (u128, u128),
+    base: u128,
+    digits: u32,
+    repeats: u32,
+) -> Option<(u128, RangeInclusive<u128>)> {
+    let block_len = digits / repeats;
+    let block_pow = base.checked_pow(block_len);
+    // horner's method: m = m * base^len + 1, once per repeat
+    let multiplier =
+        (0..repeats).try_fold(0u128, |m, _| m.checked_mul(block_pow?)?.checked_add(1))?;
+    // no leading zeroes in the block
+    let smallest = base.pow(block_len - 1);
+    let largest = block_pow.map_or(u128::MAX, |pow| pow - 1);
+
+    let lo = smallest.max(range.0.div_ceil(multiplier));
+    let hi = largest.min(range.1 / multiplier);
+    (lo <= hi).then_some((multiplier, lo..=hi))
+}
+
+// all the ids with `digits` digits that are a block repeated `repeats` times
+fn repetitions(range: (u128, u128), base: u128, digits: u32, repeats: u32) -> Tally {
+    let Some((multiplier, blocks)) = blocks(range, base, digits, repeats) else {
+        return Tally::default();
+    };
+    let (lo, hi) = blocks.into_inner();
+    let count = hi - lo + 1;
+    // arithmetic series, halving whichever factor is even
+    let block_sum = if count % 2 == 0 {
+        (count / 2) * (lo + hi)
+    } else {
+        count * ((lo + hi) / 2)
+    };
+    Tally {
+        count,
+        sum: block_sum.checked_mul(multiplier),
+    }
+}
+
+// how many digits the biggest number in the range has
+fn max_digits(range: (u128, u128), base: u128) -> u32 {
+    range.1.max(1).ilog(base) + 1
+}
+
+// ids in the range that are some block repeated between 2 and max_repeats
+// times. an id can be a repetition in more than one way (111111 is 1 six times,
+// 11 three times and 111 twice), so for every digit count the ids are split up
+// by the most times their block repeats. if a number is a repetition in two
+// ways its block lengths are both periods, and by the fine-wilf theorem so is
+// their gcd, so it's in exactly one of those groups
+fn tally(range: (u128, u128), base: u128, max_repeats: u32) -> Tally {
+    assert!(base >= 2, "base {base} doesn't make sense");
+    let mut total = Tally::default();
+    for digits in 2..=max_digits(range, base) {
+        let divisors = (2..=digits)
+            .filter(|repeats| digits.is_multiple_of(*repeats))
+            .collect::<Vec<_>>();
+        // exactly[i] = ids whose block repeats divisors[i] times and no more.
+        // anything repeated k times is also repeated d times for d dividing k,
+        // so take those out going from the largest down
+        let mut exactly = vec![Tally::default(); divisors.len()];
+        for i in (0..divisors.len()).rev() {
+            let mut tally = repetitions(range, base, digits, divisors[i]);
+            for j in i + 1..divisors.len() {
+                if divisors[j].is_multiple_of(divisors[i]) {
+                    tally = tally.sub(exactly[j]);
                 }
             }
+            exactly[i] = tally;
+        }
+        for (i, &most) in divisors.iter().enumerate() {
+            if (2..=most.min(max_repeats)).any(|repeats| most.is_multiple_of(repeats)) {
+                total = total.add(exactly[i]);
+            }
+        }
+    }
+    total
+}
+
+// same ids as tally, but listed out in order
+fn list(range: (u128, u128), base: u128, max_repeats: u32) -> Vec<u128> {
+    let mut ids = Vec::new();
+    for digits in 2..=max_digits(range, base) {
+        for repeats in 2..=digits.min(max_repeats) {
+            if !digits.is_multiple_of(repeats) {
+                continue;
+            }
+            if let Some((multiplier, blocks)) = blocks(range, base, digits, repeats) {
+                ids.extend(blocks.map(|block| block * multiplier));
+            }
         }
     }
     ids.sort_unstable();
     ids.dedup();
-    ids.iter().sum::<u64>()
+    ids
+}
+
+fn solve(ranges: &RangeSet, max_repeats: u32) -> u128 {
+    ranges
+        .iter()
+        .map(|range| {
+            let range = (*range.start() as u128, *range.end() as u128);
+            let tally = tally(range, 10, max_repeats);
+            // the puzzle ranges only hold a handful of ids, so debug builds can
+            // afford to check the inclusion-exclusion against listing them
+            debug_assert_eq!(
+                tally.sum,
+                Some(list(range, 10, max_repeats).iter().sum::<u128>())
+            );
+            tally
+        })
+        .fold(Tally::default(), Tally::add)
+        .sum
+        .expect("sum overflowed u128")
 }
 
 pub fn part1(input: String) -> String {
@@ -57,6 +179,58 @@ pub fn part2(input: String) -> String {
 
 #[cfg(test)]
 mod tests {
+    use super::{list, tally};
+    use crate::util::TestRng;
+
+    fn is_repetition(mut id: u128, base: u128, max_repeats: u32) -> bool {
+        let mut digits = Vec::new();
+        while id > 0 {
+            digits.push(id % base);
+            id /= base;
+        }
+        let len = digits.len();
+        (2..=len.min(max_repeats as usize))
+            .filter(|repeats| len % repeats == 0)
+            .any(|repeats| {
+                let block = len / repeats;
+                (block..len).all(|i| digits[i] == digits[i - block])
+            })
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        let mut rng = TestRng::new(987654321);
+        let mut random = |below: u64| rng.below(below);
+        for _ in 0..300 {
+            let base = random(15) as u128 + 2;
+            let max_repeats = random(6) as u32 + 2;
+            let magnitude = random(18);
+            let start = random(1 << magnitude) as u128;
+            let end = start + random(5000) as u128;
+
+            let expected = (start..=end)
+                .filter(|&id| is_repetition(id, base, max_repeats))
+                .collect::<Vec<_>>();
+            let found = tally((start, end), base, max_repeats);
+            assert_eq!(found.count, expected.len() as u128);
+            assert_eq!(found.sum, Some(expected.iter().sum::<u128>()));
+            assert_eq!(list((start, end), base, max_repeats), expected);
+        }
+    }
+
+    #[test]
+    fn huge_ranges() {
+        // every 128 bit number made of a repeated 64 bit block
+        let found = tally((0, u128::MAX), 2, 2);
+        assert_eq!(found.count, (1 << 64) - 1);
+        assert_eq!(found.sum, None);
+        // the largest id is all ones
+        assert_eq!(
+            *list((u128::MAX - 10, u128::MAX), 2, 2).last().unwrap(),
+            u128::MAX
+        );
+    }
+
     #[test]
     fn sample_p1() {
         use super::part1;
@@ -1,4 +1,7 @@
-use crate::util::all_adjacent_in_bounds;
+use crate::util::{
+    all_adjacent_in_bounds,
+    render::{Frame, Visualizer},
+};
 use arrayvec::ArrayVec;
 
 enum Tile {
@@ -66,9 +69,22 @@ pub fn part2(input: String) -> String {
         })
         .collect();
 
+    let mut vis = Visualizer::open("2025_day04_p2");
+    let grid_frame = |grid: &[ArrayVec<Tile, 140>]| {
+        Frame::from_grid(grid, |tile| match tile {
+            Tile::Paper => '@',
+            Tile::Empty => '.',
+        })
+    };
+    if let Some(vis) = &mut vis {
+        vis.frame(&grid_frame(&grid));
+    }
+
     let mut total = 0;
     loop {
         let mut loop_total = 0;
+        // rolls removed this round, only tracked for visualization
+        let mut removed = vec![];
         for y in 0..grid.len() {
             for x in 0..grid.len() {
                 if matches!(grid[y][x], Tile::Empty) {
@@ -78,9 +94,19 @@ pub fn part2(input: String) -> String {
                 if can_access(&grid, x, y) {
                     loop_total += 1;
                     grid[y][x] = Tile::Empty;
+                    if vis.is_some() {
+                        removed.push((y, x));
+                    }
                 }
             }
         }
+        if let Some(vis) = &mut vis {
+            let mut frame = grid_frame(&grid);
+            for pos in removed {
+                frame.set(pos, 'x');
+            }
+            vis.frame(&frame);
+        }
         if loop_total == 0 {
             break;
         } else {
@@ -1,119 +1,164 @@
-pub fn part1(input: String) -> String {
-    let input: Vec<Vec<char>> = input.lines().map(|line| line.chars().collect()).collect();
-
-    let width = input[0].len();
-    // actual input is 1 more row than test
-    let height = input.len();
-    assert!(
-        input.iter().all(|line| line.len() == width),
-        "malformed input"
-    );
-
-    let mut total = 0;
-    // solved similar to part 2
-    for (col_idx, operator) in input[height - 1]
-        .iter()
-        .enumerate()
-        .filter(|v| !v.1.is_ascii_whitespace())
-    {
-        let mut col_width = 0;
-        for idx_elem in &input[height - 1][(col_idx + 1)..] {
-            if idx_elem.is_whitespace() {
-                col_width += 1;
-            } else {
-                break;
-            }
-        }
-        // edge case: right edge
-        if col_width + col_idx == width - 1 {
-            col_width += 1;
+use std::fmt;
+
+use num_bigint::BigInt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Max,
+    Min,
+}
+
+impl Operator {
+    fn parse(token: &str) -> Self {
+        match token {
+            "+" => Operator::Add,
+            "-" => Operator::Sub,
+            "*" => Operator::Mul,
+            "/" => Operator::Div,
+            "max" => Operator::Max,
+            "min" => Operator::Min,
+            _ => panic!("unknown operator {token:?}"),
         }
+    }
 
-        let mut numbers = Vec::new();
-        // this part differs from p2. we just take numbers like a human would
-        // instead of the right to left column stuff
-        for row in input.iter().take(height - 1) {
-            let number = row[col_idx..col_idx + col_width]
-                .iter()
-                .filter_map(|c| {
-                    if c.is_ascii_whitespace() {
-                        None
-                    } else {
-                        Some(c.to_digit(10).unwrap() as u64)
-                    }
+    // applied left to right, so 8 2 2 with - is (8 - 2) - 2. None if it
+    // divides by zero
+    fn apply(self, numbers: &[u64]) -> Option<BigInt> {
+        let (first, rest) = numbers.split_first().expect("problem without numbers");
+        rest.iter()
+            .map(|&n| BigInt::from(n))
+            .try_fold(BigInt::from(*first), |a, b| {
+                Some(match self {
+                    Operator::Add => a + b,
+                    Operator::Sub => a - b,
+                    Operator::Mul => a * b,
+                    Operator::Div if b == BigInt::ZERO => return None,
+                    Operator::Div => a / b,
+                    Operator::Max => a.max(b),
+                    Operator::Min => a.min(b),
                 })
-                .reduce(|a, b| a * 10 + b)
-                .unwrap();
-            numbers.push(number);
+            })
+    }
+}
+
+// the worksheet has a problem that divides by zero, counting problems from
+// the left starting at 0
+#[derive(Debug, PartialEq, Eq)]
+struct DivisionByZero(usize);
+
+impl fmt::Display for DivisionByZero {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "problem {} divides by zero", self.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Reading {
+    // every row of a problem is a number, like a human would read it
+    Rows,
+    // every column is a number with its digits going down, and the columns are
+    // read right to left
+    Columns,
+}
+
+#[derive(Debug)]
+struct Problem {
+    numbers: Vec<u64>,
+    operator: Operator,
+}
+
+impl Problem {
+    fn solve(&self) -> Option<BigInt> {
+        self.operator.apply(&self.numbers)
+    }
+}
+
+// reads the digits and ignores the spaces around them. None if there aren't
+// any digits at all
+fn number(cells: impl Iterator<Item = char>) -> Option<u64> {
+    let digits = cells.filter(|c| *c != ' ').collect::<String>();
+    (!digits.is_empty()).then(|| {
+        digits
+            .parse()
+            .unwrap_or_else(|_| panic!("{digits:?} isn't a number"))
+    })
+}
+
+// the worksheet is a grid where problems are separated by columns that are
+// empty all the way down, and the last row has the operators
+fn parse(input: &str, reading: Reading) -> Vec<Problem> {
+    // blank lines at the end would be taken for the operators
+    let lines: Vec<Vec<char>> = input
+        .trim_end()
+        .lines()
+        .map(|line| line.chars().collect())
+        .collect();
+    let (operators, rows) = lines.split_last().expect("empty worksheet");
+    // lines don't have to be padded to the same width
+    let width = lines.iter().map(|line| line.len()).max().unwrap();
+    let at = |row: &[char], x: usize| row.get(x).copied().unwrap_or(' ');
+    let blank = |x: usize| lines.iter().all(|line| at(line, x) == ' ');
+
+    let mut problems = Vec::new();
+    let mut x = 0;
+    while x < width {
+        if blank(x) {
+            x += 1;
+            continue;
         }
-        match operator {
-            '*' => total += numbers.into_iter().reduce(|a, b| a * b).unwrap(),
-            '+' => total += numbers.into_iter().reduce(|a, b| a + b).unwrap(),
-            _ => unreachable!(),
+        let start = x;
+        while x < width && !blank(x) {
+            x += 1;
         }
+        let columns = start..x;
+
+        let operator = columns
+            .clone()
+            .map(|x| at(operators, x))
+            .collect::<String>();
+        let numbers = match reading {
+            Reading::Rows => rows
+                .iter()
+                .filter_map(|row| number(columns.clone().map(|x| at(row, x))))
+                .collect(),
+            Reading::Columns => columns
+                .rev()
+                .filter_map(|x| number(rows.iter().map(|row| at(row, x))))
+                .collect(),
+        };
+        problems.push(Problem {
+            numbers,
+            operator: Operator::parse(operator.trim()),
+        });
     }
-    total.to_string()
+    problems
 }
 
-pub fn part2(input: String) -> String {
-    let input: Vec<Vec<char>> = input.lines().map(|line| line.chars().collect()).collect();
-
-    let width = input[0].len();
-    // actual input is 1 more row than test
-    let height = input.len();
-    assert!(
-        input.iter().all(|line| line.len() == width),
-        "malformed input"
-    );
-
-    let mut total = 0;
-    // we use the operator row as our index and find the column width (which is
-    // variable) by counting until the next column
-    for (col_idx, operator) in input[height - 1]
+fn solve(input: &str, reading: Reading) -> Result<BigInt, DivisionByZero> {
+    parse(input, reading)
         .iter()
         .enumerate()
-        .filter(|v| !v.1.is_ascii_whitespace())
-    {
-        let mut col_width = 0;
-        for idx_elem in &input[height - 1][(col_idx + 1)..] {
-            if idx_elem.is_whitespace() {
-                col_width += 1;
-            } else {
-                break;
-            }
-        }
-        // edge case: right edge
-        if col_width + col_idx == width - 1 {
-            col_width += 1;
-        }
+        .map(|(i, problem)| problem.solve().ok_or(DivisionByZero(i)))
+        .sum()
+}
 
-        let mut combined = Vec::new();
-        // numbers are read from right to left
-        for x in (0..col_width).rev() {
-            let mut numbers = [0; 4];
-            for y in 0..(height - 1) {
-                if let Some(n) = input[y][col_idx + x].to_digit(10) {
-                    numbers[y] = n as u64;
-                }
-            }
-            // the input doesn't seem to have any zeroes anywhere
-            combined.push(
-                // concatenate digits
-                numbers
-                    .into_iter()
-                    .filter(|&v| v != 0)
-                    .reduce(|a, b| a * 10 + b)
-                    .unwrap(),
-            );
-        }
-        match operator {
-            '*' => total += combined.into_iter().reduce(|a, b| a * b).unwrap(),
-            '+' => total += combined.into_iter().reduce(|a, b| a + b).unwrap(),
-            _ => unreachable!(),
-        }
+fn answer(input: &str, reading: Reading) -> String {
+    match solve(input, reading) {
+        Ok(total) => total.to_string(),
+        Err(err) => format!("error: {err}"),
     }
+}
 
-    total.to_string()
+pub fn part1(input: String) -> String {
+    answer(&input, Reading::Rows)
+}
+
+pub fn part2(input: String) -> String {
+    answer(&input, Reading::Columns)
 }
 
 #[cfg(test)]
@@ -141,4 +186,52 @@ mod tests {
             .to_string();
         assert_eq!(part2(input), "3263827");
     }
+
+    #[test]
+    fn more_operators() {
+        use super::{part1, part2};
+
+        // products that don't fit in a u64, and lines that aren't padded
+        let input = "99999999999 20 7
+99999999999  6 9
+*           -  max"
+            .to_string();
+        assert_eq!(part1(input.clone()), "9999999999800000000024");
+        assert_eq!(part2(input), "8953382542587164451182");
+
+        let input = "120 3   15
+  4 9   72
+/   min -"
+            .to_string();
+        assert_eq!(part1(input.clone()), "-24");
+        assert_eq!(part2(input), "76");
+    }
+
+    #[test]
+    fn division_by_zero() {
+        use super::{DivisionByZero, Reading, part1, solve};
+
+        let input = "8 6
+2 0
++ /";
+        assert_eq!(solve(input, Reading::Rows), Err(DivisionByZero(1)));
+        assert_eq!(part1(input.to_string()), "error: problem 1 divides by zero");
+        // read as columns nothing gets divided: 82 and 60 on their own
+        assert_eq!(solve(input, Reading::Columns).unwrap(), 142.into());
+    }
+
+    #[test]
+    fn trailing_blank_lines() {
+        use super::{part1, part2};
+
+        let input = "123 328  51 64 
+ 45 64  387 23 
+  6 98  215 314
+*   +   *   +  
+
+"
+        .to_string();
+        assert_eq!(part1(input.clone()), "4277556");
+        assert_eq!(part2(input), "3263827");
+    }
 }
@@ -1,49 +1,298 @@
-use std::{iter::repeat_n, mem::swap};
-
-use arrayvec::ArrayVec;
-
-fn solve(input: String) -> (u64, u64) {
-    let mut grid: Vec<ArrayVec<char, 141>> = input.lines().map(|it| it.chars().collect()).collect();
-    let mut beams = Vec::from_iter(grid[0].iter().map(|&c| (c == 'S') as _));
-    let mut new_beams = Vec::from_iter(repeat_n(0, beams.len()));
-    let width = grid[0].len();
-
-    assert!(
-        grid.iter().all(|line| line.len() == width),
-        "malformed input"
-    );
-
-    let mut split_counter = 0;
-    for row in grid.iter_mut() {
-        // we start from 1 timeline, and on each split there is a possibility of
-        // arriving in the same place multiple times through different routes
-        for (i, (&elem, &prev_beam)) in row.iter().zip(&beams).enumerate() {
-            if elem == '^' && prev_beam > 0 {
-                new_beams[i - 1] += prev_beam;
-                new_beams[i + 1] += prev_beam;
-                split_counter += 1;
-            } else if prev_beam > 0 {
-                new_beams[i] += prev_beam;
+use num_bigint::BigUint;
+
+use crate::util::{
+    Direction,
+    render::{Frame, Visualizer},
+};
+
+// a beam entering a cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Beam {
+    pos: (usize, usize),
+    dir: Direction,
+}
+
+struct Manifold {
+    grid: Vec<Vec<char>>,
+    height: usize,
+    width: usize,
+}
+
+struct Simulation {
+    // splitters hit by at least one beam
+    splits: usize,
+    // how many ways a single particle can end up leaving the manifold. None if
+    // some beam goes round in a loop, since then there's infinitely many
+    timelines: Option<BigUint>,
+    // how many timelines go through each cell. if there's a loop that's
+    // infinite, so it's how many beams (one per direction) go through instead
+    heatmap: Vec<Vec<BigUint>>,
+}
+
+impl Manifold {
+    fn parse(input: &str) -> Self {
+        let grid: Vec<Vec<char>> = input.lines().map(|line| line.chars().collect()).collect();
+        let width = grid[0].len();
+        assert!(
+            grid.iter().all(|line| line.len() == width),
+            "malformed input"
+        );
+        Manifold {
+            height: grid.len(),
+            width,
+            grid,
+        }
+    }
+
+    // every S shoots a beam downwards
+    fn sources(&self) -> Vec<Beam> {
+        (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (y, x)))
+            .filter(|&(y, x)| self.grid[y][x] == 'S')
+            .map(|pos| Beam {
+                pos,
+                dir: Direction::Down,
+            })
+            .collect()
+    }
+
+    fn step(&self, pos: (usize, usize), dir: Direction) -> Option<Beam> {
+        let pos = dir.apply_checked(pos, self.height, self.width)?;
+        Some(Beam { pos, dir })
+    }
+
+    fn is_splitter(&self, beam: Beam) -> bool {
+        let vertical = matches!(beam.dir, Direction::Up | Direction::Down);
+        match self.grid[beam.pos.0][beam.pos.1] {
+            '^' => true,
+            '|' => !vertical,
+            '-' => vertical,
+            _ => false,
+        }
+    }
+
+    // where the beam goes after its cell. None means it left the manifold
+    fn next(&self, beam: Beam) -> Vec<Option<Beam>> {
+        use Direction::*;
+        let Beam { pos, dir } = beam;
+        let sideways = match dir {
+            Up | Down => [Left, Right],
+            Left | Right => [Up, Down],
+        };
+        match self.grid[pos.0][pos.1] {
+            // the beam carries on from both sides of the splitter
+            '^' => sideways
+                .into_iter()
+                .map(|side| {
+                    let beside = side.apply_checked(pos, self.height, self.width)?;
+                    self.step(beside, dir)
+                })
+                .collect(),
+            '/' => {
+                let dir = match dir {
+                    Up => Right,
+                    Right => Up,
+                    Down => Left,
+                    Left => Down,
+                };
+                vec![self.step(pos, dir)]
             }
+            '\\' => {
+                let dir = match dir {
+                    Up => Left,
+                    Left => Up,
+                    Down => Right,
+                    Right => Down,
+                };
+                vec![self.step(pos, dir)]
+            }
+            '|' | '-' if self.is_splitter(beam) => sideways
+                .into_iter()
+                .map(|side| self.step(pos, side))
+                .collect(),
+            _ => vec![self.step(pos, dir)],
         }
+    }
 
-        swap(&mut beams, &mut new_beams);
-        new_beams = Vec::from_iter(repeat_n(0, beams.len()));
+    fn index(&self, beam: Beam) -> usize {
+        (beam.pos.0 * self.width + beam.pos.1) * 4 + beam.dir.index()
     }
 
-    (split_counter, beams.into_iter().sum::<_>())
+    fn simulate(&self, sources: &[Beam]) -> Simulation {
+        // find every beam that can happen
+        let mut next: Vec<Option<Vec<Option<Beam>>>> = vec![None; self.height * self.width * 4];
+        let mut reachable = Vec::new();
+        let mut stack = sources.to_vec();
+        while let Some(beam) = stack.pop() {
+            let i = self.index(beam);
+            if next[i].is_some() {
+                continue;
+            }
+            let after = self.next(beam);
+            stack.extend(after.iter().flatten());
+            next[i] = Some(after);
+            reachable.push(beam);
+        }
+
+        let mut split_cells = reachable
+            .iter()
+            .filter(|beam| self.is_splitter(**beam))
+            .map(|beam| beam.pos)
+            .collect::<Vec<_>>();
+        split_cells.sort_unstable();
+        split_cells.dedup();
+
+        // kahn's algorithm. if some beams never get their turn they're on a loop
+        let mut incoming = vec![0; next.len()];
+        for beam in &reachable {
+            for after in next[self.index(*beam)].as_ref().unwrap().iter().flatten() {
+                incoming[self.index(*after)] += 1;
+            }
+        }
+        let mut order = Vec::with_capacity(reachable.len());
+        let mut ready = reachable
+            .iter()
+            .filter(|beam| incoming[self.index(**beam)] == 0)
+            .copied()
+            .collect::<Vec<_>>();
+        while let Some(beam) = ready.pop() {
+            order.push(beam);
+            for after in next[self.index(beam)].as_ref().unwrap().iter().flatten() {
+                incoming[self.index(*after)] -= 1;
+                if incoming[self.index(*after)] == 0 {
+                    ready.push(*after);
+                }
+            }
+        }
+
+        let mut heatmap = vec![vec![BigUint::ZERO; self.width]; self.height];
+        if order.len() < reachable.len() {
+            for beam in &reachable {
+                heatmap[beam.pos.0][beam.pos.1] += 1u32;
+            }
+            return Simulation {
+                splits: split_cells.len(),
+                timelines: None,
+                heatmap,
+            };
+        }
+
+        // every way to reach a beam carries on to everything after it
+        let mut counts = vec![BigUint::ZERO; next.len()];
+        for source in sources {
+            counts[self.index(*source)] += 1u32;
+        }
+        let mut timelines = BigUint::ZERO;
+        for beam in order {
+            let count = std::mem::take(&mut counts[self.index(beam)]);
+            for after in next[self.index(beam)].as_ref().unwrap() {
+                match after {
+                    Some(after) => counts[self.index(*after)] += &count,
+                    None => timelines += &count,
+                }
+            }
+            heatmap[beam.pos.0][beam.pos.1] += count;
+        }
+        Simulation {
+            splits: split_cells.len(),
+            timelines: Some(timelines),
+            heatmap,
+        }
+    }
+
+    // brighter digits for busier cells, on a log scale
+    fn heatmap_frame(&self, heatmap: &[Vec<BigUint>]) -> Frame {
+        let max_bits = heatmap
+            .iter()
+            .flatten()
+            .map(|c| c.bits())
+            .max()
+            .unwrap_or(0);
+        let mut frame = Frame::from_grid(&self.grid, |&c| c);
+        for (y, row) in heatmap.iter().enumerate() {
+            for (x, count) in row.iter().enumerate() {
+                if self.grid[y][x] == '.' && count.bits() > 0 {
+                    let level = (count.bits() * 9 / max_bits).max(1) as u32;
+                    frame.set((y, x), char::from_digit(level, 10).unwrap());
+                }
+            }
+        }
+        frame
+    }
+}
+
+fn solve(input: String) -> Simulation {
+    let manifold = Manifold::parse(&input);
+    let simulation = manifold.simulate(&manifold.sources());
+    if let Some(mut vis) = Visualizer::open("2025_day07") {
+        vis.frame(&manifold.heatmap_frame(&simulation.heatmap));
+    }
+    simulation
 }
 
 pub fn part1(input: String) -> String {
-    solve(input).0.to_string()
+    solve(input).splits.to_string()
 }
 
 pub fn part2(input: String) -> String {
-    solve(input).1.to_string()
+    solve(input)
+        .timelines
+        .expect("the beams go round in a loop")
+        .to_string()
 }
 
 #[cfg(test)]
 mod tests {
+    use super::{Beam, Manifold};
+    use crate::util::Direction;
+    use num_bigint::BigUint;
+
+    #[test]
+    fn sideways_and_loops() {
+        // a beam coming in from the left gets split up and down, and both
+        // halves leave the grid
+        let manifold = Manifold::parse(
+            "...
+.|.
+...",
+        );
+        let source = Beam {
+            pos: (1, 0),
+            dir: Direction::Right,
+        };
+        let simulation = manifold.simulate(&[source]);
+        assert_eq!(simulation.splits, 1);
+        assert_eq!(simulation.timelines, Some(BigUint::from(2u32)));
+        assert_eq!(simulation.heatmap[1][1], BigUint::from(1u32));
+        assert_eq!(simulation.heatmap[0][1], BigUint::from(1u32));
+
+        // mirrors sending the beam round forever
+        let manifold = Manifold::parse(
+            "/.\\
+...
+\\./",
+        );
+        let simulation = manifold.simulate(&[Beam {
+            pos: (0, 1),
+            dir: Direction::Right,
+        }]);
+        assert_eq!(simulation.timelines, None);
+        assert_eq!(simulation.heatmap[0][1], BigUint::from(1u32));
+        assert_eq!(simulation.heatmap[1][1], BigUint::ZERO);
+    }
+
+    #[test]
+    fn many_timelines() {
+        // 130 rows of splitters is more timelines than fit in a u128
+        let mut input = String::from("...S...\n");
+        for _ in 0..130 {
+            input.push_str("..^.^..\n...^...\n");
+        }
+        let manifold = Manifold::parse(input.trim_end());
+        let simulation = manifold.simulate(&manifold.sources());
+        assert!(simulation.timelines.unwrap().bits() > 128);
+    }
+
     #[test]
     fn sample_p1() {
         use super::part1;
@@ -1,7 +1,9 @@
 use std::{cmp::Reverse, collections::BinaryHeap};
 
 use itertools::Itertools;
-use rustc_hash::FxHashSet;
+use rustc_hash::FxHashMap;
+
+use crate::util::UnionFind;
 
 #[derive(PartialEq, Eq, Clone, Copy, Debug)]
 struct Coord {
@@ -34,161 +36,236 @@ fn parse_input(input: String) -> Vec<Coord> {
         .collect::<Vec<_>>()
 }
 
-fn connections_iter(boxes: &[Coord]) -> impl Iterator<Item = (usize, usize)> {
-    // getting the distances from a min heap is much faster compared to sorting
-    // an iterator
-    let mut heap = BinaryHeap::from_iter(
-        (0..boxes.len())
-            .tuple_combinations()
-            .map(|(i, j)| Reverse((dist(&boxes[i], &boxes[j]), i, j))),
-    );
-    // nightly seems to have into_sorted_iter, but other than that the only
-    // option is into_sorted_vec, which would make sense but it's close to 6x as
-    // slow. This works like into_sorted_iter
-    (0..heap.len()).map(move |_| {
-        let v = heap.pop().unwrap();
-        (v.0.1, v.0.2)
-    })
+// a k-d tree stored implicitly: every slice of `order` has its splitting point
+// in the middle, with the smaller half before it and the bigger half after
+struct KdTree<'a> {
+    boxes: &'a [Coord],
+    order: Vec<usize>,
 }
 
-pub fn part1(input: String) -> String {
-    let boxes = parse_input(input);
-    // test input has a different amount of connections
-    let connection_count = if boxes.len() == 20 { 10 } else { 1000 };
-
-    let connections_to_make = connections_iter(&boxes);
+fn axis(coord: &Coord, depth: usize) -> i64 {
+    match depth % 3 {
+        0 => coord.x,
+        1 => coord.y,
+        _ => coord.z,
+    }
+}
 
-    let mut connections = vec![];
-    // we partly merge connections. This merges e.g. (i,j) and (j, k), but not (i,j), (k, l), and (l, i)
-    for (i, j) in connections_to_make.take(connection_count) {
-        if connections.is_empty() {
-            connections.push(FxHashSet::from_iter([i, j]));
-        }
-        let mut was_inserted = false;
-        for circuit in connections.iter_mut() {
-            if circuit.contains(&i) {
-                circuit.insert(j);
-                was_inserted = true;
-            }
-            if circuit.contains(&j) {
-                circuit.insert(i);
-                was_inserted = true;
+impl<'a> KdTree<'a> {
+    fn new(boxes: &'a [Coord]) -> Self {
+        fn build(order: &mut [usize], boxes: &[Coord], depth: usize) {
+            if order.len() <= 1 {
+                return;
             }
+            let mid = order.len() / 2;
+            order.select_nth_unstable_by_key(mid, |&i| axis(&boxes[i], depth));
+            let (left, right) = order.split_at_mut(mid);
+            build(left, boxes, depth + 1);
+            build(&mut right[1..], boxes, depth + 1);
         }
-        if !was_inserted {
-            connections.push(FxHashSet::from_iter([i, j]));
-        }
+
+        let mut order = (0..boxes.len()).collect_vec();
+        build(&mut order, boxes, 0);
+        KdTree { boxes, order }
     }
 
-    // iteratively merge the unmerged circuits from the last step
-    'outer: loop {
-        for i in 0..connections.len() {
-            let mut to_combine = vec![i];
-            for j in 0..connections.len() {
-                if i == j {
-                    continue;
-                }
-                if connections[i]
-                    .intersection(&connections[j])
-                    .next()
-                    .is_some()
-                {
-                    to_combine.push(j);
-                }
-            }
-            if to_combine.len() > 1 {
-                let mut combined = FxHashSet::default();
-                for idx in to_combine.into_iter().sorted().rev() {
-                    combined.extend(connections.remove(idx));
-                }
-                connections.push(combined);
-                // we modified the list we're iterating over, so we should restart
-                continue 'outer;
+    // the k boxes closest to box i (not counting itself) as (dist, index),
+    // sorted. ties go to the lower index, so asking for more neighbours later
+    // gives the same ones first
+    fn nearest(&self, i: usize, k: usize) -> Vec<(i64, usize)> {
+        let mut best = BinaryHeap::with_capacity(k + 1);
+        self.search(&self.order, 0, i, k, &mut best);
+        best.into_sorted_vec()
+    }
+
+    fn search(
+        &self,
+        order: &[usize],
+        depth: usize,
+        i: usize,
+        k: usize,
+        best: &mut BinaryHeap<(i64, usize)>,
+    ) {
+        if order.is_empty() {
+            return;
+        }
+        let mid = order.len() / 2;
+        let node = order[mid];
+        if node != i {
+            best.push((dist(&self.boxes[i], &self.boxes[node]), node));
+            if best.len() > k {
+                best.pop();
             }
         }
-        break;
-    }
 
-    // multiply the sizes of the 3 largest circuits
-    connections
-        .into_iter()
-        .map(|v| v.len())
-        .sorted()
-        .rev()
-        .take(3)
-        .reduce(|a, b| a * b)
-        .unwrap()
-        .to_string()
+        let offset = axis(&self.boxes[i], depth) - axis(&self.boxes[node], depth);
+        let (near, far) = if offset < 0 {
+            (&order[..mid], &order[mid + 1..])
+        } else {
+            (&order[mid + 1..], &order[..mid])
+        };
+        self.search(near, depth + 1, i, k, best);
+        // the other side can only have something closer if the splitting plane
+        // is. equal distances still count because of the tie break
+        if best.len() < k || offset.pow(2) <= best.peek().unwrap().0 {
+            self.search(far, depth + 1, i, k, best);
+        }
+    }
 }
 
-struct UnionFind {
-    parents: Vec<usize>,
-    ranks: Vec<usize>,
-    set_count: usize,
+// every pair of boxes, closest first. each box keeps a short sorted list of its
+// neighbours and the closest unused one from every list sits in a heap. when a
+// list runs out it's fetched again twice as long, so far away pairs are only
+// looked at if they're actually needed
+struct Pairs<'a> {
+    tree: KdTree<'a>,
+    // (dist, i, j) so ties come out in the same order as sorting all pairs
+    heap: BinaryHeap<Reverse<(i64, usize, usize)>>,
+    neighbours: Vec<Vec<(i64, usize)>>,
+    // how many of each box's neighbours are already in the heap or used
+    used: Vec<usize>,
 }
 
-// https://www.geeksforgeeks.org/dsa/introduction-to-disjoint-set-data-structure-or-union-find-algorithm/
-impl UnionFind {
-    fn find(&mut self, v: usize) -> usize {
-        let root = self.parents[v];
-        // compress
-        if self.parents[root] != root {
-            self.parents[v] = self.find(root);
-            return self.parents[v];
+impl<'a> Pairs<'a> {
+    fn new(boxes: &'a [Coord]) -> Self {
+        let mut pairs = Pairs {
+            tree: KdTree::new(boxes),
+            heap: BinaryHeap::with_capacity(boxes.len()),
+            neighbours: vec![Vec::new(); boxes.len()],
+            used: vec![0; boxes.len()],
+        };
+        for i in 0..boxes.len() {
+            pairs.advance(i);
         }
-        root
+        pairs
     }
-    fn unite(&mut self, x: usize, y: usize) {
-        let x_root = self.find(x);
-        let y_root = self.find(y);
-
-        // already united
-        if x_root == y_root {
-            return;
-        }
 
-        self.set_count -= 1;
-        if self.ranks[x_root] < self.ranks[y_root] {
-            self.parents[x_root] = y_root;
-        } else if self.ranks[x_root] > self.ranks[y_root] {
-            self.parents[y_root] = x_root;
-        } else {
-            self.parents[y_root] = x_root;
-            self.ranks[x_root] += 1;
+    // puts the next neighbour of box i in the heap
+    fn advance(&mut self, i: usize) {
+        let used = self.used[i];
+        if used == self.neighbours[i].len() {
+            // already had every other box
+            if used == self.tree.boxes.len() - 1 {
+                return;
+            }
+            self.neighbours[i] = self.tree.nearest(i, (used * 2).max(8));
         }
+        let (d, j) = self.neighbours[i][used];
+        self.used[i] += 1;
+        self.heap.push(Reverse((d, i, j)));
     }
+}
 
-    fn are_all_merged(&mut self) -> bool {
-        self.set_count == 1
-    }
+impl Iterator for Pairs<'_> {
+    type Item = (usize, usize);
 
-    fn new(count: usize) -> Self {
-        Self {
-            parents: Vec::from_iter(0..count),
-            ranks: vec![0; count],
-            set_count: count,
+    fn next(&mut self) -> Option<(usize, usize)> {
+        loop {
+            let Reverse((_, i, j)) = self.heap.pop()?;
+            self.advance(i);
+            // every pair shows up from both ends, only keep one of them
+            if i < j {
+                return Some((i, j));
+            }
         }
     }
 }
 
-pub fn part2(input: String) -> String {
+// kruskal's algorithm, but only as far as it needs to go. yields the pairs that
+// join two circuits and stops once everything is a single circuit
+fn kruskal(boxes: &[Coord]) -> impl Iterator<Item = (usize, usize)> + '_ {
+    let mut uf = UnionFind::new(boxes.len());
+    Pairs::new(boxes)
+        .map_while(move |(i, j)| {
+            if uf.are_all_merged() {
+                return None;
+            }
+            Some(uf.unite(i, j).then_some((i, j)))
+        })
+        .flatten()
+}
+
+pub fn part1(input: String) -> String {
     let boxes = parse_input(input);
-    let mut connections_to_make = connections_iter(&boxes);
+    // test input has a different amount of connections
+    let connection_count = if boxes.len() == 20 { 10 } else { 1000 };
 
-    // // start with everything being its own parent
     let mut uf = UnionFind::new(boxes.len());
-
-    let mut i = 0;
-    let mut j = 0;
-    while !uf.are_all_merged() {
-        (i, j) = connections_to_make.next().unwrap();
+    for (i, j) in Pairs::new(&boxes).take(connection_count) {
         uf.unite(i, j);
     }
+
+    // multiply the sizes of the 3 largest circuits
+    let mut sizes = FxHashMap::default();
+    for i in 0..boxes.len() {
+        *sizes.entry(uf.find(i)).or_insert(0) += 1;
+    }
+    sizes
+        .into_values()
+        .sorted()
+        .rev()
+        .take(3)
+        .product::<usize>()
+        .to_string()
+}
+
+pub fn part2(input: String) -> String {
+    let boxes = parse_input(input);
+    let (i, j) = kruskal(&boxes)
+        .last()
+        .expect("there should be at least 2 boxes");
     (boxes[i].x * boxes[j].x).to_string()
 }
 
 #[cfg(test)]
 mod tests {
+    use super::{Coord, Pairs, dist, kruskal};
+    use crate::util::TestRng;
+    use itertools::Itertools;
+
+    fn random_boxes(count: usize) -> Vec<Coord> {
+        let mut rng = TestRng::new(42);
+        // small range so there are plenty of ties
+        let mut random = || rng.below(50) as i64;
+        let mut boxes: Vec<Coord> = Vec::new();
+        while boxes.len() < count {
+            let coord = Coord {
+                x: random(),
+                y: random(),
+                z: random(),
+            };
+            if !boxes.contains(&coord) {
+                boxes.push(coord);
+            }
+        }
+        boxes
+    }
+
+    #[test]
+    fn pairs_in_order() {
+        let boxes = random_boxes(150);
+        let expected = (0..boxes.len())
+            .tuple_combinations()
+            .sorted_by_key(|&(i, j)| (dist(&boxes[i], &boxes[j]), i, j))
+            .collect_vec();
+        assert_eq!(Pairs::new(&boxes).collect_vec(), expected);
+    }
+
+    #[test]
+    fn kruskal_spans_everything() {
+        let boxes = random_boxes(300);
+        let edges = kruskal(&boxes).collect_vec();
+        assert_eq!(edges.len(), boxes.len() - 1);
+        // the last edge of a minimum spanning tree is its longest
+        let longest = edges
+            .iter()
+            .map(|&(i, j)| dist(&boxes[i], &boxes[j]))
+            .max()
+            .unwrap();
+        let (i, j) = *edges.last().unwrap();
+        assert_eq!(dist(&boxes[i], &boxes[j]), longest);
+    }
+
     #[test]
     fn sample_p1() {
         use super::part1;
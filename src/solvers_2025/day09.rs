@@ -1,5 +1,9 @@
+use std::cmp::Reverse;
+
 use itertools::Itertools;
 
+use crate::util::geometry::Polygon;
+
 fn parse_input(input: String) -> Vec<(i64, i64)> {
     input
         .lines()
@@ -24,113 +28,19 @@ pub fn part1(input: String) -> String {
         .to_string()
 }
 
-fn point_inside(vertical_edges: &[(f64, f64, f64)], point: (f64, f64)) -> bool {
-    // https://en.wikipedia.org/wiki/Point_in_polygon
-
-    // if horizontal ray crosses the edges an odd amount of times, the point is
-    // inside our shape
-    let mut intersections = 0u64;
-    for &(vx, vy_min, vy_max) in vertical_edges {
-        if vx > point.0 && vy_min <= point.1 && point.1 < vy_max {
-            intersections += 1;
-        }
-    }
-    intersections % 2 == 1
-}
-
-fn intersects(
-    vertical_edges: &[(f64, f64, f64)],
-    horizon_edges: &[(f64, f64, f64)],
-    r_min_x: f64,
-    r_min_y: f64,
-    r_max_x: f64,
-    r_max_y: f64,
-) -> bool {
-    for &(vx, vy_min, vy_max) in vertical_edges {
-        if r_min_x < vx && vx < r_max_x {
-            let ol_min = vy_min.max(r_min_y);
-            let ol_max = vy_max.min(r_max_y);
-            if ol_min < ol_max {
-                return true;
-            }
-        }
-    }
-
-    for &(hx_min, hx_max, hy) in horizon_edges {
-        if r_min_y < hy && hy < r_max_y {
-            let ol_min = hx_min.max(r_min_x);
-            let ol_max = hx_max.min(r_max_x);
-            if ol_min < ol_max {
-                return true;
-            }
-        }
-    }
-
-    false
-}
-
-// https://aoc.just2good.co.uk/2025/9 i couldnt do it myself. even copying this
-// took 2 hours of debugging
 pub fn part2(input: String) -> String {
-    let corners = input
-        .lines()
-        .map(|line| {
-            let (l, r) = line
-                .split_once(",")
-                .unwrap_or_else(|| panic!("line {line} not formatted correctly"));
-            (l.parse::<_>().unwrap(), r.parse::<_>().unwrap())
-        })
-        .collect::<Vec<(f64, f64)>>();
-
-    // [(x, y1, y2)] y1<y2
-    let mut vertical_edges = vec![];
-    // [(x1, x2, y)] x1<x2
-    let mut horizon_edges = vec![];
-
-    for i in 0..corners.len() {
-        let a = corners[i];
-        let b = corners[(i + 1) % corners.len()];
-        // vertical edge
-        if a.0 == b.0 {
-            vertical_edges.push((a.0, a.1.min(b.1), a.1.max(b.1)));
-        } else {
-            assert!(a.1 == b.1);
-            horizon_edges.push((a.0.min(b.0), a.0.max(b.0), a.1));
-        }
-    }
-
-    let mut max = 0.0;
-    for (a, b) in corners.iter().tuple_combinations() {
-        let width = (a.0 - b.0).abs() + 1.0;
-        let height = (a.1 - b.1).abs() + 1.0;
-        let area = width * height;
+    let corners = parse_input(input);
+    let index = Polygon::new(corners.clone()).index();
 
-        if area <= max {
-            continue;
-        }
-
-        // top left
-        let r_min_x = a.0.min(b.0);
-        let r_min_y = a.1.min(b.1);
-        // bottom right
-        let r_max_x = a.0.max(b.0);
-        let r_max_y = a.1.max(b.1);
-
-        // point slightly to the bottom right of the top left of the rectangle
-        if point_inside(&vertical_edges, (r_min_x + 0.5, r_min_y + 0.5))
-            && !intersects(
-                &vertical_edges,
-                &horizon_edges,
-                r_min_x,
-                r_min_y,
-                r_max_x,
-                r_max_y,
-            )
-        {
-            max = area;
-        }
-    }
-    max.to_string()
+    corners
+        .iter()
+        .tuple_combinations()
+        .map(|(&a, &b)| ((1 + (a.0 - b.0).abs()) * (1 + (a.1 - b.1).abs()), a, b))
+        .sorted_unstable_by_key(|&(area, _, _)| Reverse(area))
+        .find(|&(_, a, b)| index.contains_rect(a, b))
+        .map(|(area, _, _)| area)
+        .unwrap()
+        .to_string()
 }
 
 #[cfg(test)]
@@ -1,7 +1,17 @@
 use itertools::Itertools;
 use regex::Regex;
+#[cfg(feature = "z3")]
 use z3::{Optimize, SatResult, ast::Int};
 
+#[cfg(not(feature = "z3"))]
+use std::{cmp::Ordering, ops::RangeInclusive};
+
+#[cfg(not(feature = "z3"))]
+use crate::util::{
+    ext_gcd,
+    linalg::{Matrix, Rational},
+};
+
 // my input only has max 10 lights
 const LIGHT_LEN: usize = 10;
 type Lights = [bool; LIGHT_LEN];
@@ -67,86 +77,361 @@ pub fn part1(input: String) -> String {
         .to_string()
 }
 
-pub fn part2(input: String) -> String {
-    let joltage_re = Regex::new(r"\{(.*?)\}").unwrap();
-    let button_re = Regex::new(r"\((.*?)\)").unwrap();
-    let answers = input.lines().map(|line| {
-        let mut buttons = button_re
-            .captures_iter(line)
-            .map(|c| c.extract())
-            .map(|(_, [cap])| cap.split(",").map(|s| s.parse::<_>().unwrap()).collect())
-            .collect::<Vec<Vec<_>>>();
-
-        let (_, [joltage_str]) = joltage_re.captures(line).unwrap().extract();
-        let mut joltages = joltage_str
-            .split(",")
-            .map(|w| w.parse::<i64>().unwrap())
-            .collect_vec();
+// the minimum amount of button presses so that every counter ends up at its
+// joltage, or None if that isn't possible
+#[cfg(feature = "z3")]
+fn min_presses(buttons: &[Vec<usize>], joltages: &[i64]) -> Option<i64> {
+    let mut buttons = buttons.to_vec();
+    let mut joltages = joltages.to_vec();
 
-        // z3 seems to break if a button isnt allowed to be pressed. It should
-        // work because the assertions are >= 0 and not > 0, but it doesnt so
-        // idk
-        while let Some((idx, _)) = joltages
-            .iter()
-            .enumerate()
-            .find(|(_, joltage)| **joltage == 0)
-        {
-            for button in buttons.iter_mut() {
-                button.retain(|dest_joltage| *dest_joltage != idx);
-                for dest_joltage in button.iter_mut() {
-                    if *dest_joltage >= idx {
-                        *dest_joltage -= 1;
-                    }
+    // z3 seems to break if a button isnt allowed to be pressed. It should
+    // work because the assertions are >= 0 and not > 0, but it doesnt so
+    // idk
+    while let Some((idx, _)) = joltages
+        .iter()
+        .enumerate()
+        .find(|(_, joltage)| **joltage == 0)
+    {
+        for button in buttons.iter_mut() {
+            button.retain(|dest_joltage| *dest_joltage != idx);
+            for dest_joltage in button.iter_mut() {
+                if *dest_joltage >= idx {
+                    *dest_joltage -= 1;
                 }
             }
-            joltages.remove(idx);
         }
+        joltages.remove(idx);
+    }
 
-        // our button press count integers
-        let presses = buttons
+    // our button press count integers
+    let presses = buttons
+        .iter()
+        .enumerate()
+        .map(|(i, _)| Int::new_const(format!("press{i}")))
+        .collect_vec();
+
+    let optimize = Optimize::new();
+
+    for press in presses.iter() {
+        optimize.assert(&press.ge(0))
+    }
+
+    for (i, &joltage) in joltages.iter().filter(|v| **v > 0).enumerate() {
+        let sum = buttons
             .iter()
             .enumerate()
-            .map(|(i, _)| Int::new_const(format!("press{i}")))
-            .collect_vec();
+            .fold(Int::from_i64(0), |acc, (idx, button)| {
+                if button.contains(&(i)) {
+                    acc + &presses[idx]
+                } else {
+                    acc
+                }
+            });
+        optimize.assert(&sum.eq(joltage));
+    }
 
-        let optimize = Optimize::new();
+    // magic
+    optimize.minimize(&Int::add(&presses));
 
-        for press in presses.iter() {
-            optimize.assert(&press.ge(0))
+    match optimize.check(&[]) {
+        SatResult::Sat => {
+            let model = optimize.get_model().unwrap();
+            Some(
+                presses
+                    .iter()
+                    .map(|press| model.eval(press, true).unwrap().as_i64().unwrap())
+                    .sum::<i64>(),
+            )
         }
+        SatResult::Unknown => panic!("optimisation failed with unknown Sat"),
+        SatResult::Unsat => None,
+    }
+}
 
-        for (i, &joltage) in joltages.iter().filter(|v| **v > 0).enumerate() {
-            let sum = buttons
+// pressing button j adds 1 to every counter it's wired to, so with x_j presses
+// we need A x = joltages, where A[i][j] is 1 if button j is wired to counter i.
+// the reduced row echelon form of that (see day-10-testing.py) gives every
+// pivot button as a function of the free buttons:
+//   d * x_pivot = rhs - sum(coefs[f] * x_free[f])
+// with everything scaled to integers
+#[cfg(not(feature = "z3"))]
+struct PivotRow {
+    d: i64,
+    rhs: i64,
+    coefs: Vec<i64>,
+}
+
+#[cfg(not(feature = "z3"))]
+struct Search {
+    rows: Vec<PivotRow>,
+    // inclusive range of presses for each free button
+    bounds: Vec<(i64, i64)>,
+    // the total press count times `scale` is base + sum(weights[f] * x_free[f])
+    weights: Vec<i64>,
+    scale: i64,
+    // how much each row can still grow from the free buttons that are left,
+    // indexed [row][free]
+    row_slack: Vec<Vec<i64>>,
+    // the most that the free buttons that are left can lower the total
+    cost_slack: Vec<i64>,
+    best: i64,
+}
+
+#[cfg(not(feature = "z3"))]
+impl Search {
+    // branch and bound over the free buttons, which are each bounded by the
+    // joltages of the counters they're wired to
+    fn search(&mut self, k: usize, partial: &mut [i64], total: i64) {
+        if k == self.bounds.len() {
+            // no free buttons at all, so the pivot buttons could still end up
+            // being pressed a fractional or negative amount
+            if partial
                 .iter()
-                .enumerate()
-                .fold(Int::from_i64(0), |acc, (idx, button)| {
-                    if button.contains(&(i)) {
-                        acc + &presses[idx]
-                    } else {
-                        acc
-                    }
-                });
-            optimize.assert(&sum.eq(joltage));
+                .zip(&self.rows)
+                .all(|(value, row)| value % row.d == 0 && *value >= 0)
+            {
+                self.best = self.best.min(total);
+            }
+            return;
+        }
+
+        // narrow down how many times this button can be pressed so that every
+        // pivot button can still be pressed a non-negative amount with the
+        // help of the free buttons after this one
+        let (mut lo, mut hi) = self.bounds[k];
+        for ((&value, row), slack) in partial.iter().zip(&self.rows).zip(&self.row_slack) {
+            let reachable = value + slack[k + 1];
+            match row.coefs[k].cmp(&0) {
+                Ordering::Greater => hi = hi.min(reachable.div_euclid(row.coefs[k])),
+                Ordering::Less => lo = lo.max(-reachable.div_euclid(-row.coefs[k])),
+                Ordering::Equal if reachable < 0 => return,
+                Ordering::Equal => {}
+            }
+        }
+        // and so that we can still do better than the best answer so far
+        let room = self.best.saturating_sub(total + self.cost_slack[k + 1]) - 1;
+        match self.weights[k].cmp(&0) {
+            Ordering::Greater => hi = hi.min(room.div_euclid(self.weights[k])),
+            Ordering::Less => lo = lo.max(-room.div_euclid(-self.weights[k])),
+            Ordering::Equal if room < 0 => return,
+            Ordering::Equal => {}
         }
 
-        // magic
-        optimize.minimize(&Int::add(&presses));
+        // going from the cheap end finds good answers early, which lets the
+        // check above skip more
+        let mut range = lo..=hi;
+        let descending = self.weights[k] < 0;
+        let next = |range: &mut RangeInclusive<i64>| {
+            if descending {
+                range.next_back()
+            } else {
+                range.next()
+            }
+        };
 
-        match optimize.check(&[]) {
-            SatResult::Sat => {
-                let model = optimize.get_model().unwrap();
-                presses
+        if k + 1 == self.bounds.len() {
+            // the total only depends on this button now, so the first one
+            // that works is the best one
+            while let Some(presses) = next(&mut range) {
+                if partial
                     .iter()
-                    .map(|press| model.eval(press, true).unwrap().as_i64().unwrap())
-                    .sum::<i64>()
+                    .zip(&self.rows)
+                    .all(|(value, row)| (value - row.coefs[k] * presses) % row.d == 0)
+                {
+                    self.best = self.best.min(total + self.weights[k] * presses);
+                    return;
+                }
+            }
+            return;
+        }
+
+        while let Some(presses) = next(&mut range) {
+            if total + self.weights[k] * presses + self.cost_slack[k + 1] >= self.best {
+                // everything after this is even more expensive
+                break;
+            }
+            for (value, row) in partial.iter_mut().zip(&self.rows) {
+                *value -= row.coefs[k] * presses;
             }
-            SatResult::Unknown => {
-                panic!("optimisation failed for line {line} with unknown Sat")
+            self.search(k + 1, partial, total + self.weights[k] * presses);
+            for (value, row) in partial.iter_mut().zip(&self.rows) {
+                *value += row.coefs[k] * presses;
             }
-            SatResult::Unsat => {
-                panic!("optimisation failed for line {line} with unsatisfiable")
+        }
+    }
+}
+
+#[cfg(not(feature = "z3"))]
+fn min_presses(buttons: &[Vec<usize>], joltages: &[i64]) -> Option<i64> {
+    let matrix = Matrix::from_rows((0..joltages.len()).map(|counter| {
+        buttons
+            .iter()
+            .map(move |button| i64::from(button.contains(&counter)))
+    }));
+    let target = joltages.iter().map(|&j| Rational::from(j)).collect_vec();
+    let (rref, pivots) = matrix.augment(&target).rref();
+    if pivots.last() == Some(&buttons.len()) {
+        // 0 = 1, so nothing works
+        return None;
+    }
+
+    let mut free = (0..buttons.len())
+        .filter(|button| !pivots.contains(button))
+        .collect_vec();
+    // a button can't be pressed more times than the lowest joltage it adds
+    // to. buttons that don't add to anything are pointless to press
+    let mut bounds = free
+        .iter()
+        .map(|&f| {
+            let upper = buttons[f]
+                .iter()
+                .filter_map(|&counter| joltages.get(counter))
+                .min()
+                .copied()
+                .unwrap_or(0);
+            (0, upper)
+        })
+        .collect_vec();
+
+    let rows = (0..pivots.len())
+        .map(|y| {
+            let values = [rref[(y, buttons.len())]]
+                .into_iter()
+                .chain(free.iter().map(|&f| rref[(y, f)]))
+                .collect_vec();
+            let d = values.iter().fold(1, |d, v| lcm(d, v.denom()));
+            let mut ints = values
+                .iter()
+                .map(|v| i64::try_from(v.numer() * (d / v.denom())).unwrap());
+            PivotRow {
+                d: d.try_into().unwrap(),
+                rhs: ints.next().unwrap(),
+                coefs: ints.collect(),
+            }
+        })
+        .collect_vec();
+
+    if !tighten_bounds(&rows, &mut bounds) {
+        return None;
+    }
+
+    // searching the buttons with the most options first seems to work best
+    let order = (0..free.len())
+        .sorted_by_key(|&f| bounds[f].0 - bounds[f].1)
+        .collect_vec();
+    free = order.iter().map(|&f| free[f]).collect();
+    bounds = order.iter().map(|&f| bounds[f]).collect();
+    let rows = rows
+        .into_iter()
+        .map(|row| PivotRow {
+            coefs: order.iter().map(|&f| row.coefs[f]).collect(),
+            ..row
+        })
+        .collect_vec();
+
+    // total = sum(x_pivot) + sum(x_free), which is scaled to stay an integer
+    let scale = rows.iter().fold(1, |acc, row| lcm(acc, row.d.into())) as i64;
+    let base = rows.iter().map(|row| scale / row.d * row.rhs).sum::<i64>();
+    let weights = (0..free.len())
+        .map(|f| {
+            scale
+                - rows
+                    .iter()
+                    .map(|row| scale / row.d * row.coefs[f])
+                    .sum::<i64>()
+        })
+        .collect_vec();
+
+    let mut row_slack = vec![vec![0; free.len() + 1]; rows.len()];
+    for (row, slack) in rows.iter().zip(row_slack.iter_mut()) {
+        for f in (0..free.len()).rev() {
+            let (lo, hi) = bounds[f];
+            slack[f] = slack[f + 1] + (-row.coefs[f] * lo).max(-row.coefs[f] * hi);
+        }
+    }
+    let mut cost_slack = vec![0; free.len() + 1];
+    for f in (0..free.len()).rev() {
+        let (lo, hi) = bounds[f];
+        cost_slack[f] = cost_slack[f + 1] + (weights[f] * lo).min(weights[f] * hi);
+    }
+
+    let mut search = Search {
+        bounds,
+        weights,
+        scale,
+        row_slack,
+        cost_slack,
+        best: i64::MAX,
+        rows,
+    };
+    let mut partial = search.rows.iter().map(|row| row.rhs).collect_vec();
+    search.search(0, &mut partial, base);
+
+    (search.best != i64::MAX).then(|| search.best / search.scale)
+}
+
+// every pivot button has to be pressed a non-negative amount, which limits
+// the free buttons too. this narrows their ranges until nothing changes, and
+// returns false if some range becomes empty
+#[cfg(not(feature = "z3"))]
+fn tighten_bounds(rows: &[PivotRow], bounds: &mut [(i64, i64)]) -> bool {
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for row in rows {
+            // the smallest that sum(coefs[f] * x_free[f]) can be
+            let min_terms = |skip: usize, bounds: &[(i64, i64)]| {
+                (0..bounds.len())
+                    .filter(|&f| f != skip)
+                    .map(|f| (row.coefs[f] * bounds[f].0).min(row.coefs[f] * bounds[f].1))
+                    .sum::<i64>()
+            };
+            for k in 0..bounds.len() {
+                // rhs - coefs[k] * x_k - rest >= 0, where the rest is as small as
+                // possible
+                let room = row.rhs - min_terms(k, bounds);
+                let (lo, hi) = bounds[k];
+                let tightened = match row.coefs[k].cmp(&0) {
+                    Ordering::Greater => (lo, hi.min(room.div_euclid(row.coefs[k]))),
+                    Ordering::Less => (lo.max(-room.div_euclid(-row.coefs[k])), hi),
+                    Ordering::Equal => (lo, hi),
+                };
+                if tightened.0 > tightened.1 {
+                    return false;
+                }
+                if tightened != (lo, hi) {
+                    bounds[k] = tightened;
+                    changed = true;
+                }
             }
         }
+    }
+    true
+}
+
+#[cfg(not(feature = "z3"))]
+fn lcm(a: i128, b: i128) -> i128 {
+    a / ext_gcd(a, b).0 * b
+}
+
+pub fn part2(input: String) -> String {
+    let joltage_re = Regex::new(r"\{(.*?)\}").unwrap();
+    let button_re = Regex::new(r"\((.*?)\)").unwrap();
+    let answers = input.lines().map(|line| {
+        let buttons = button_re
+            .captures_iter(line)
+            .map(|c| c.extract())
+            .map(|(_, [cap])| cap.split(",").map(|s| s.parse::<_>().unwrap()).collect())
+            .collect::<Vec<Vec<_>>>();
+
+        let (_, [joltage_str]) = joltage_re.captures(line).unwrap().extract();
+        let joltages = joltage_str
+            .split(",")
+            .map(|w| w.parse::<i64>().unwrap())
+            .collect_vec();
+
+        min_presses(&buttons, &joltages)
+            .unwrap_or_else(|| panic!("no button presses work for line {line}"))
     });
 
     answers.sum::<i64>().to_string()
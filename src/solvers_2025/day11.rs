@@ -1,66 +1,445 @@
-use itertools::Itertools;
-use rustc_hash::FxHashMap;
+use std::fmt;
 
-fn parse_input(input: &str) -> FxHashMap<&str, Vec<&str>> {
-    input
-        .lines()
-        .map(|line| {
-            let (source, rest) = line.split_once(": ").unwrap();
-            (source, rest.split_ascii_whitespace().collect_vec())
-        })
-        .collect::<_>()
+use num_bigint::BigUint;
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::util::Interner;
+
+struct Graph<'a> {
+    names: Interner<'a>,
+    edges: Vec<Vec<usize>>,
 }
 
-// simple dfs with memoisation
-fn _simple_path<'a>(
-    source: &'a str,
-    target: &'a str,
-    connections: &FxHashMap<&'a str, Vec<&'a str>>,
-    memo: &mut FxHashMap<&'a str, u64>,
-) -> u64 {
-    if source == target {
-        return 1;
+impl<'a> Graph<'a> {
+    fn parse(input: &'a str) -> Self {
+        let mut graph = Graph {
+            names: Interner::new(),
+            edges: Vec::new(),
+        };
+        for line in input.lines() {
+            let (source, rest) = line.split_once(": ").unwrap();
+            let source = graph.add(source);
+            for target in rest.split_ascii_whitespace() {
+                let target = graph.add(target);
+                graph.edges[source].push(target);
+            }
+        }
+        graph
+    }
+
+    fn add(&mut self, name: &'a str) -> usize {
+        let node = self.names.intern(name) as usize;
+        if node == self.edges.len() {
+            self.edges.push(Vec::new());
+        }
+        node
+    }
+
+    fn id(&self, name: &str) -> usize {
+        self.names
+            .get(name)
+            .unwrap_or_else(|| panic!("there's no {name} node")) as usize
     }
-    if let Some(count) = memo.get(source) {
-        return *count;
+
+    fn name(&self, node: usize) -> &'a str {
+        self.names.label(node as u32)
     }
-    let mut path_count = 0;
-    if let Some(adj) = connections.get(source) {
-        for &node in adj {
-            path_count += _simple_path(node, target, connections, memo);
+}
+
+// paths from one node to another that go through all the waypoints and none of
+// the forbidden nodes. the puzzle only needs unordered waypoints, the rest is
+// there for asking other questions about the same graph
+struct PathQuery<'a> {
+    from: &'a str,
+    to: &'a str,
+    waypoints: Vec<&'a str>,
+    // whether the waypoints have to be visited in the order they're given
+    ordered: bool,
+    forbidden: Vec<&'a str>,
+}
+
+impl<'a> PathQuery<'a> {
+    fn new(from: &'a str, to: &'a str) -> Self {
+        PathQuery {
+            from,
+            to,
+            waypoints: Vec::new(),
+            ordered: false,
+            forbidden: Vec::new(),
         }
     }
-    memo.insert(source, path_count);
-    path_count
+
+    fn through(mut self, waypoints: &[&'a str]) -> Self {
+        self.waypoints.extend(waypoints);
+        self
+    }
+
+    #[allow(dead_code)]
+    fn in_order(mut self) -> Self {
+        self.ordered = true;
+        self
+    }
+
+    #[allow(dead_code)]
+    fn avoiding(mut self, forbidden: &[&'a str]) -> Self {
+        self.forbidden.extend(forbidden);
+        self
+    }
 }
-fn simple_path<'a>(
-    source: &'a str,
-    target: &'a str,
-    connections: &FxHashMap<&'a str, Vec<&'a str>>,
-) -> u64 {
-    let mut memo = FxHashMap::default();
-    _simple_path(source, target, connections, &mut memo)
+
+// there's a loop somewhere between the start and the end, so there are
+// infinitely many paths
+#[derive(Debug, PartialEq, Eq)]
+struct Cycle<'a>(Vec<&'a str>);
+
+impl fmt::Display for Cycle<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "cycle {} -> {}", self.0.join(" -> "), self.0[0])
+    }
 }
 
-pub fn part1(input: String) -> String {
-    let connections = parse_input(&input);
+// the answer to a query. a "state" is which waypoints a path has been through
+// so far: a bitmask of them, or if they're ordered how many of them
+struct Paths<'g, 'a> {
+    graph: &'g Graph<'a>,
+    query: &'g PathQuery<'a>,
+    from: usize,
+    to: usize,
+    // waypoint node -> its index in the query
+    waypoints: FxHashMap<usize, usize>,
+    // ways[node][state] = paths from node to the end, arriving at node in
+    // that state
+    ways: Vec<Vec<BigUint>>,
+}
+
+impl<'a> Graph<'a> {
+    fn paths<'g>(&'g self, query: &'g PathQuery<'a>) -> Result<Paths<'g, 'a>, Cycle<'a>> {
+        let node_count = self.names.len();
+        let states = if query.ordered {
+            query.waypoints.len() + 1
+        } else {
+            1 << query.waypoints.len()
+        };
+        let mut paths = Paths {
+            graph: self,
+            query,
+            from: self.id(query.from),
+            to: self.id(query.to),
+            waypoints: query
+                .waypoints
+                .iter()
+                .enumerate()
+                .map(|(i, name)| (self.id(name), i))
+                .collect(),
+            ways: vec![vec![BigUint::ZERO; states]; node_count],
+        };
+
+        // only nodes that can get to the end matter, otherwise a loop off to
+        // the side would count as a cycle
+        let forbidden: FxHashSet<usize> =
+            query.forbidden.iter().map(|name| self.id(name)).collect();
+        let mut reverse = vec![Vec::new(); node_count];
+        for (source, targets) in self.edges.iter().enumerate() {
+            for &target in targets {
+                reverse[target].push(source);
+            }
+        }
+        let mut useful = vec![false; node_count];
+        let mut stack = vec![paths.to];
+        while let Some(node) = stack.pop() {
+            if useful[node] || forbidden.contains(&node) {
+                continue;
+            }
+            useful[node] = true;
+            stack.extend(&reverse[node]);
+        }
+        if !useful[paths.from] {
+            return Ok(paths);
+        }
+
+        // dfs that finishes nodes after everything they point to, so the ways
+        // can be worked out backwards along the way
+        #[derive(Clone, Copy, PartialEq)]
+        enum Mark {
+            New,
+            Active,
+            Done,
+        }
+        fn visit(
+            paths: &mut Paths,
+            node: usize,
+            useful: &[bool],
+            marks: &mut [Mark],
+            stack: &mut Vec<usize>,
+        ) -> Result<(), usize> {
+            marks[node] = Mark::Active;
+            stack.push(node);
+            if node != paths.to {
+                for &next in &paths.graph.edges[node] {
+                    if !useful[next] {
+                        continue;
+                    }
+                    match marks[next] {
+                        Mark::New => visit(paths, next, useful, marks, stack)?,
+                        // back to something we're still inside of
+                        Mark::Active => return Err(next),
+                        Mark::Done => {}
+                    }
+                }
+            }
+            stack.pop();
+            marks[node] = Mark::Done;
+            paths.finish(node);
+            Ok(())
+        }
 
-    simple_path("you", "out", &connections).to_string()
+        let mut marks = vec![Mark::New; node_count];
+        let mut stack = Vec::new();
+        let from = paths.from;
+        visit(&mut paths, from, &useful, &mut marks, &mut stack).map_err(|start| {
+            let at = stack.iter().position(|&node| node == start).unwrap();
+            Cycle(stack[at..].iter().map(|&node| self.name(node)).collect())
+        })?;
+        Ok(paths)
+    }
 }
 
-pub fn part2(input: String) -> String {
-    let connections = parse_input(&input);
+impl<'a> Paths<'_, 'a> {
+    // the state after stepping onto a node, or None if the path can't go there
+    fn enter(&self, state: usize, node: usize) -> Option<usize> {
+        let Some(&i) = self.waypoints.get(&node) else {
+            return Some(state);
+        };
+        if !self.query.ordered {
+            Some(state | 1 << i)
+        } else if state == i {
+            Some(state + 1)
+        } else {
+            // too early, and in a dag there's no coming back later
+            None
+        }
+    }
+
+    fn done(&self) -> usize {
+        if self.query.ordered {
+            self.query.waypoints.len()
+        } else {
+            (1 << self.query.waypoints.len()) - 1
+        }
+    }
 
-    [("svr", "fft"), ("fft", "dac"), ("dac", "out")]
-        .map(|(from, to)| simple_path(from, to, &connections))
-        .into_iter()
-        .reduce(|a, b| a * b)
-        .unwrap()
+    fn finish(&mut self, node: usize) {
+        for state in 0..self.ways[node].len() {
+            let ways = if node == self.to {
+                BigUint::from((state == self.done()) as u32)
+            } else {
+                self.graph.edges[node]
+                    .iter()
+                    .filter_map(|&next| Some(&self.ways[next][self.enter(state, next)?]))
+                    .sum()
+            };
+            self.ways[node][state] = ways;
+        }
+    }
+
+    fn start(&self) -> Option<usize> {
+        self.enter(0, self.from)
+    }
+
+    fn count(&self) -> BigUint {
+        self.start()
+            .map_or(BigUint::ZERO, |state| self.ways[self.from][state].clone())
+    }
+
+    // every path, sorted the same way as nth counts them: by the order of the
+    // edges in the input. not needed for the answers, as the real inputs have
+    // far too many paths to list
+    #[allow(dead_code)]
+    fn list(&self) -> Vec<Vec<&'a str>> {
+        fn walk<'a>(
+            paths: &Paths<'_, 'a>,
+            node: usize,
+            state: usize,
+            path: &mut Vec<&'a str>,
+            out: &mut Vec<Vec<&'a str>>,
+        ) {
+            path.push(paths.graph.name(node));
+            if node == paths.to {
+                out.push(path.clone());
+            } else {
+                for &next in &paths.graph.edges[node] {
+                    if let Some(next_state) = paths.enter(state, next)
+                        && paths.ways[next][next_state] > BigUint::ZERO
+                    {
+                        walk(paths, next, next_state, path, out);
+                    }
+                }
+            }
+            path.pop();
+        }
+
+        let mut out = Vec::new();
+        if self.count() > BigUint::ZERO {
+            walk(
+                self,
+                self.from,
+                self.start().unwrap(),
+                &mut Vec::new(),
+                &mut out,
+            );
+        }
+        out
+    }
+
+    // the nth path without listing the ones before it. a random n below the
+    // count gives a uniformly random path. like list, nothing in the puzzle
+    // asks for this
+    #[allow(dead_code)]
+    fn nth(&self, mut n: BigUint) -> Option<Vec<&'a str>> {
+        if n >= self.count() {
+            return None;
+        }
+        let (mut node, mut state) = (self.from, self.start()?);
+        let mut path = vec![self.graph.name(node)];
+        while node != self.to {
+            for &next in &self.graph.edges[node] {
+                let Some(next_state) = self.enter(state, next) else {
+                    continue;
+                };
+                let ways = &self.ways[next][next_state];
+                if n < *ways {
+                    (node, state) = (next, next_state);
+                    break;
+                }
+                n -= ways;
+            }
+            path.push(self.graph.name(node));
+        }
+        Some(path)
+    }
+}
+
+fn count(input: &str, query: PathQuery) -> String {
+    let graph = Graph::parse(input);
+    graph
+        .paths(&query)
+        .unwrap_or_else(|cycle| panic!("infinitely many paths, found a {cycle}"))
+        .count()
         .to_string()
 }
 
+pub fn part1(input: String) -> String {
+    count(&input, PathQuery::new("you", "out"))
+}
+
+pub fn part2(input: String) -> String {
+    count(
+        &input,
+        PathQuery::new("svr", "out").through(&["fft", "dac"]),
+    )
+}
+
 #[cfg(test)]
 mod tests {
+    use super::{Cycle, Graph, PathQuery};
+    use num_bigint::BigUint;
+
+    const GRAPH: &str = "svr: aaa bbb
+aaa: fft
+fft: ccc
+bbb: tty
+tty: ccc
+ccc: ddd eee
+ddd: hub
+hub: fff
+eee: dac
+dac: fff
+fff: ggg hhh
+ggg: out
+hhh: out";
+
+    #[test]
+    fn waypoints() {
+        let graph = Graph::parse(GRAPH);
+        let count = |query: PathQuery| graph.paths(&query).unwrap().count();
+
+        assert_eq!(count(PathQuery::new("svr", "out")), BigUint::from(8u32));
+        let query = PathQuery::new("svr", "out").through(&["dac", "fft"]);
+        assert_eq!(count(query), BigUint::from(2u32));
+        // fft always comes first
+        let query = PathQuery::new("svr", "out")
+            .through(&["dac", "fft"])
+            .in_order();
+        assert_eq!(count(query), BigUint::ZERO);
+        let query = PathQuery::new("svr", "out")
+            .through(&["fft", "dac"])
+            .in_order();
+        assert_eq!(count(query), BigUint::from(2u32));
+        let query = PathQuery::new("svr", "out").avoiding(&["fft", "hhh"]);
+        assert_eq!(count(query), BigUint::from(2u32));
+        let query = PathQuery::new("svr", "out")
+            .through(&["ccc"])
+            .avoiding(&["ccc"]);
+        assert_eq!(count(query), BigUint::ZERO);
+    }
+
+    #[test]
+    fn listing() {
+        let graph = Graph::parse(GRAPH);
+        let query = PathQuery::new("svr", "out").through(&["dac"]);
+        let paths = graph.paths(&query).unwrap();
+        let all = paths.list();
+        assert_eq!(all.len(), 4);
+        assert!(all.iter().all(|path| path.contains(&"dac")));
+        assert_eq!(
+            all[0],
+            vec![
+                "svr", "aaa", "fft", "ccc", "eee", "dac", "fff", "ggg", "out"
+            ]
+        );
+        for (i, path) in all.iter().enumerate() {
+            assert_eq!(paths.nth(BigUint::from(i)).as_ref(), Some(path));
+        }
+        assert_eq!(paths.nth(BigUint::from(4u32)), None);
+    }
+
+    #[test]
+    fn cycles() {
+        // hhh can get back to fff
+        let input = format!("{GRAPH}\nhhh: fff");
+        let graph = Graph::parse(&input);
+        let query = PathQuery::new("svr", "out");
+        assert_eq!(graph.paths(&query).err(), Some(Cycle(vec!["fff", "hhh"])));
+        // unless it's not allowed through
+        let query = PathQuery::new("svr", "out").avoiding(&["hhh"]);
+        assert_eq!(graph.paths(&query).unwrap().count(), BigUint::from(4u32));
+
+        // a loop that never gets anywhere doesn't matter
+        let input = format!("{GRAPH}\nhhh: zzz\nzzz: yyy\nyyy: zzz");
+        let graph = Graph::parse(&input);
+        let query = PathQuery::new("svr", "out");
+        assert_eq!(graph.paths(&query).unwrap().count(), BigUint::from(8u32));
+    }
+
+    #[test]
+    fn huge_counts() {
+        // 150 diamonds in a row is 2^150 paths
+        let mut input = String::new();
+        for i in 0..150 {
+            input.push_str(&format!(
+                "n{i}: a{i} b{i}\na{i}: n{}\nb{i}: n{}\n",
+                i + 1,
+                i + 1
+            ));
+        }
+        let graph = Graph::parse(&input);
+        let query = PathQuery::new("n0", "n150");
+        assert_eq!(
+            graph.paths(&query).unwrap().count(),
+            BigUint::from(2u32).pow(150)
+        );
+    }
+
     #[test]
     fn sample_p1() {
         use super::part1;
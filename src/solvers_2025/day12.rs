@@ -1,60 +1,184 @@
-use arrayvec::ArrayVec;
 use itertools::Itertools;
 use regex::Regex;
 
-#[derive(Debug)]
-struct Bin {
-    area: u64,
-    quantities: ArrayVec<u64, 10>,
+use crate::util::dlx::Dlx;
+
+// one orientation of a present. rows are bitmasks where bit x is column x
+#[derive(PartialEq, Eq, Hash, Clone)]
+struct Variant {
+    rows: Vec<u64>,
+    width: usize,
+}
+
+impl Variant {
+    fn from_cells(cells: &[(usize, usize)]) -> Self {
+        let min_y = cells.iter().map(|c| c.0).min().unwrap();
+        let min_x = cells.iter().map(|c| c.1).min().unwrap();
+        let height = cells.iter().map(|c| c.0).max().unwrap() - min_y + 1;
+        let width = cells.iter().map(|c| c.1).max().unwrap() - min_x + 1;
+        let mut rows = vec![0u64; height];
+        for &(y, x) in cells {
+            rows[y - min_y] |= 1 << (x - min_x);
+        }
+        Variant { rows, width }
+    }
+}
+
+struct Shape {
+    area: usize,
+    // all distinct rotations and reflections
+    variants: Vec<Variant>,
+}
+
+impl Shape {
+    fn parse(block: &str) -> Self {
+        let cells = block
+            .lines()
+            .enumerate()
+            .flat_map(|(y, line)| {
+                line.chars()
+                    .enumerate()
+                    .filter(|(_, c)| *c == '#')
+                    .map(move |(x, _)| (y as i64, x as i64))
+            })
+            .collect_vec();
+        assert!(!cells.is_empty(), "empty shape:\n{block}");
+
+        let variants = (0..8)
+            .map(|transform| {
+                let moved = cells
+                    .iter()
+                    .map(|&(y, x)| {
+                        // 4 rotations, and all of them mirrored
+                        let (y, x) = if transform >= 4 { (y, -x) } else { (y, x) };
+                        match transform % 4 {
+                            0 => (y, x),
+                            1 => (x, -y),
+                            2 => (-y, -x),
+                            _ => (-x, y),
+                        }
+                    })
+                    .collect_vec();
+                let min_y = moved.iter().map(|c| c.0).min().unwrap();
+                let min_x = moved.iter().map(|c| c.1).min().unwrap();
+                let moved = moved
+                    .into_iter()
+                    .map(|(y, x)| ((y - min_y) as usize, (x - min_x) as usize))
+                    .collect_vec();
+                Variant::from_cells(&moved)
+            })
+            .unique()
+            .collect();
+
+        Shape {
+            area: cells.len(),
+            variants,
+        }
+    }
+}
+
+fn fits(shapes: &[Shape], width: usize, height: usize, quantities: &[usize]) -> bool {
+    let present_area = quantities
+        .iter()
+        .zip(shapes)
+        .map(|(count, shape)| count * shape.area)
+        .sum::<usize>();
+    if present_area > width * height {
+        return false;
+    }
+
+    // if every present fits in its own box there's no need to think about it.
+    // this is enough for every region of the real input
+    let box_h = shapes
+        .iter()
+        .flat_map(|s| &s.variants)
+        .map(|v| v.rows.len())
+        .max();
+    let box_w = shapes
+        .iter()
+        .flat_map(|s| &s.variants)
+        .map(|v| v.width)
+        .max();
+    if let (Some(box_h), Some(box_w)) = (box_h, box_w)
+        && (width / box_w) * (height / box_h) >= quantities.iter().sum()
+    {
+        return true;
+    }
+
+    // every present is a primary column and every cell a secondary one, since
+    // cells are allowed to stay empty. copies of the same shape are
+    // interchangeable, so to not try them in every order each copy has to start
+    // on the same row as the next one or below it. every such pair gets a
+    // secondary column per row: the first copy takes the row it starts on, the
+    // second takes all the rows above its own
+    let cells = width * height;
+    let pieces = quantities.iter().sum::<usize>();
+    let pairs = pieces - quantities.iter().filter(|&&count| count > 0).count();
+    let pair_column = |pair: usize, y: usize| pieces + cells + pair * height + y;
+    let mut dlx = Dlx::new(pieces, cells + pairs * height);
+    let (mut piece, mut pair) = (0, 0);
+    for (shape, &count) in shapes.iter().zip(quantities) {
+        for copy in 0..count {
+            for variant in &shape.variants {
+                for y in 0..(height + 1).saturating_sub(variant.rows.len()) {
+                    for x in 0..(width + 1).saturating_sub(variant.width) {
+                        let mut columns = vec![piece];
+                        for (dy, row) in variant.rows.iter().enumerate() {
+                            columns.extend(
+                                (0..variant.width)
+                                    .filter(|dx| row & (1 << dx) != 0)
+                                    .map(|dx| pieces + (y + dy) * width + x + dx),
+                            );
+                        }
+                        if copy + 1 < count {
+                            columns.push(pair_column(pair, y));
+                        }
+                        if copy > 0 {
+                            columns.extend((0..y).map(|above| pair_column(pair - 1, above)));
+                        }
+                        dlx.add_row(&columns);
+                    }
+                }
+            }
+            piece += 1;
+            if copy + 1 < count {
+                pair += 1;
+            }
+        }
+    }
+    dlx.first().is_some()
 }
 
 pub fn part1(input: String) -> String {
-    let shape_re = Regex::new(r"(?s:\d:\n(.{11})\n\n)").unwrap();
-    let bin_re = Regex::new(r"(\d{1,2})x(\d{1,2}): (.+)").unwrap();
-    let shapes = shape_re
-        .captures_iter(&input)
-        .map(|caps| {
-            let (_, [shape]) = caps.extract();
-            shape.chars().filter(|c| *c == '#').count() as u64
+    let region_re = Regex::new(r"^(\d+)x(\d+): (.+)$").unwrap();
+    let (shape_blocks, region_block) = input.trim_end().rsplit_once("\n\n").unwrap();
+    let shapes = shape_blocks
+        .split("\n\n")
+        .map(|block| {
+            // skip the "0:" line
+            let (_, shape) = block.split_once('\n').unwrap();
+            Shape::parse(shape)
         })
         .collect_vec();
-    let bins = bin_re
-        .captures_iter(&input)
-        .map(|caps| {
-            let (_, [width, height, quantities]) = caps.extract();
-            let area = width.parse::<u64>().unwrap() * height.parse::<u64>().unwrap();
+
+    region_block
+        .lines()
+        .filter(|line| {
+            let (_, [width, height, quantities]) = region_re.captures(line).unwrap().extract();
             let quantities = quantities
                 .split_whitespace()
-                .map(|word| word.parse::<_>().unwrap())
-                .collect();
-            Bin { area, quantities }
+                .map(|word| word.parse().unwrap())
+                .collect_vec();
+            assert_eq!(quantities.len(), shapes.len());
+            fits(
+                &shapes,
+                width.parse().unwrap(),
+                height.parse().unwrap(),
+                &quantities,
+            )
         })
-        .collect_vec();
-    let mut count = 0;
-    for bin in bins {
-        let [simple_area, complex_area] = bin
-            .quantities
-            .iter()
-            .enumerate()
-            // test
-            .map(|(i, count)| [count * 9, count * shapes[i]])
-            .reduce(|a, b| [a[0] + b[0], a[1] + b[1]])
-            .unwrap();
-        let fits_with_simple_packing = simple_area <= bin.area;
-
-        // apparently the real input does not have situations where this is
-        // necessary. The correct answer is simply the amount of simple packing fits
-        let _possibly_fits_with_complex_packing = complex_area <= bin.area;
-        #[cfg(test)] // i dont care to actually implement it just for one test
-        if !fits_with_simple_packing && _possibly_fits_with_complex_packing {
-            return "-1".to_string();
-        }
-
-        if fits_with_simple_packing {
-            count += 1;
-        }
-    }
-    count.to_string()
+        .count()
+        .to_string()
 }
 
 pub fn part2(_: String) -> String {
@@ -101,6 +225,6 @@ mod tests {
 12x5: 1 0 1 0 2 2
 12x5: 1 0 1 0 3 2"
             .to_string();
-        assert_eq!(part1(input), "-1");
+        assert_eq!(part1(input), "2");
     }
 }
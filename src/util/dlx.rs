@@ -0,0 +1,304 @@
+// knuth's algorithm x with dancing links
+//
+// primary columns have to be covered exactly once, secondary columns at most
+// once. rows are added as lists of column indices, primary columns come first
+// (0..primary) and secondary ones after (primary..primary + secondary)
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    // stop at the first solution
+    First,
+    // collect every solution
+    All,
+    // only count them, nothing gets stored
+    Count,
+}
+
+#[derive(Debug, Default)]
+pub struct Outcome {
+    // row indices of each solution, sorted
+    pub solutions: Vec<Vec<usize>>,
+    pub count: u64,
+    // how many rows were tried
+    pub steps: u64,
+    // the search gave up early because of the step limit, so the count might be
+    // too low and "no solution" doesn't mean much
+    pub limit_hit: bool,
+}
+
+#[derive(Clone, Copy)]
+struct Node {
+    left: usize,
+    right: usize,
+    up: usize,
+    down: usize,
+    column: usize,
+    row: usize,
+}
+
+pub struct Dlx {
+    // node 0 is the root, 1..=columns are the column headers, the rest are
+    // the cells of the rows
+    nodes: Vec<Node>,
+    size: Vec<usize>,
+    columns: usize,
+    rows: usize,
+    step_limit: Option<u64>,
+}
+
+impl Dlx {
+    pub fn new(primary: usize, secondary: usize) -> Self {
+        let columns = primary + secondary;
+        let mut dlx = Dlx {
+            nodes: Vec::with_capacity(columns + 1),
+            size: vec![0; columns + 1],
+            columns,
+            rows: 0,
+            step_limit: None,
+        };
+        for node in 0..=columns {
+            // primary headers (and the root) form a ring, secondary headers
+            // point at themselves so they never get picked
+            let (left, right) = if node <= primary {
+                ((node + primary) % (primary + 1), (node + 1) % (primary + 1))
+            } else {
+                (node, node)
+            };
+            dlx.nodes.push(Node {
+                left,
+                right,
+                up: node,
+                down: node,
+                column: node,
+                row: usize::MAX,
+            });
+        }
+        dlx
+    }
+
+    pub fn with_step_limit(mut self, limit: u64) -> Self {
+        self.step_limit = Some(limit);
+        self
+    }
+
+    // returns the index of the new row
+    pub fn add_row(&mut self, columns: &[usize]) -> usize {
+        let row = self.rows;
+        self.rows += 1;
+        let first = self.nodes.len();
+        for (i, &col) in columns.iter().enumerate() {
+            assert!(col < self.columns, "column {col} out of range");
+            let header = col + 1;
+            let node = self.nodes.len();
+            let above = self.nodes[header].up;
+            self.nodes.push(Node {
+                // horizontal ring
+                left: if i == 0 {
+                    first + columns.len() - 1
+                } else {
+                    node - 1
+                },
+                right: if i == columns.len() - 1 {
+                    first
+                } else {
+                    node + 1
+                },
+                // append at the bottom of the column
+                up: above,
+                down: header,
+                column: header,
+                row,
+            });
+            self.nodes[above].down = node;
+            self.nodes[header].up = node;
+            self.size[header] += 1;
+        }
+        row
+    }
+
+    pub fn first(&mut self) -> Option<Vec<usize>> {
+        self.solve(Mode::First).solutions.pop()
+    }
+
+    pub fn all(&mut self) -> Vec<Vec<usize>> {
+        self.solve(Mode::All).solutions
+    }
+
+    pub fn count(&mut self) -> u64 {
+        self.solve(Mode::Count).count
+    }
+
+    pub fn solve(&mut self, mode: Mode) -> Outcome {
+        let mut outcome = Outcome::default();
+        let mut partial = Vec::new();
+        self.search(mode, &mut partial, &mut outcome);
+        outcome
+    }
+
+    // returns true when the search should stop
+    fn search(&mut self, mode: Mode, partial: &mut Vec<usize>, outcome: &mut Outcome) -> bool {
+        if self.nodes[0].right == 0 {
+            outcome.count += 1;
+            if mode != Mode::Count {
+                let mut solution = partial.clone();
+                solution.sort();
+                outcome.solutions.push(solution);
+            }
+            return mode == Mode::First;
+        }
+
+        // column with the fewest options left
+        let mut best = self.nodes[0].right;
+        let mut col = self.nodes[best].right;
+        while col != 0 {
+            if self.size[col] < self.size[best] {
+                best = col;
+            }
+            col = self.nodes[col].right;
+        }
+        if self.size[best] == 0 {
+            return false;
+        }
+
+        self.cover(best);
+        let mut node = self.nodes[best].down;
+        let mut stop = false;
+        while node != best {
+            if self.step_limit.is_some_and(|limit| outcome.steps >= limit) {
+                outcome.limit_hit = true;
+                stop = true;
+                break;
+            }
+            outcome.steps += 1;
+
+            partial.push(self.nodes[node].row);
+            let mut other = self.nodes[node].right;
+            while other != node {
+                self.cover(self.nodes[other].column);
+                other = self.nodes[other].right;
+            }
+            stop = self.search(mode, partial, outcome);
+            let mut other = self.nodes[node].left;
+            while other != node {
+                self.uncover(self.nodes[other].column);
+                other = self.nodes[other].left;
+            }
+            partial.pop();
+
+            if stop {
+                break;
+            }
+            node = self.nodes[node].down;
+        }
+        self.uncover(best);
+        stop
+    }
+
+    fn cover(&mut self, col: usize) {
+        // a no-op for secondary headers since they point at themselves
+        let (l, r) = (self.nodes[col].left, self.nodes[col].right);
+        self.nodes[l].right = r;
+        self.nodes[r].left = l;
+        let mut i = self.nodes[col].down;
+        while i != col {
+            let mut j = self.nodes[i].right;
+            while j != i {
+                let (u, d) = (self.nodes[j].up, self.nodes[j].down);
+                self.nodes[u].down = d;
+                self.nodes[d].up = u;
+                self.size[self.nodes[j].column] -= 1;
+                j = self.nodes[j].right;
+            }
+            i = self.nodes[i].down;
+        }
+    }
+
+    fn uncover(&mut self, col: usize) {
+        let mut i = self.nodes[col].up;
+        while i != col {
+            let mut j = self.nodes[i].left;
+            while j != i {
+                self.size[self.nodes[j].column] += 1;
+                let (u, d) = (self.nodes[j].up, self.nodes[j].down);
+                self.nodes[u].down = j;
+                self.nodes[d].up = j;
+                j = self.nodes[j].left;
+            }
+            i = self.nodes[i].up;
+        }
+        let (l, r) = (self.nodes[col].left, self.nodes[col].right);
+        self.nodes[l].right = col;
+        self.nodes[r].left = col;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the example from knuth's paper, columns A-G
+    fn knuth() -> Dlx {
+        let mut dlx = Dlx::new(7, 0);
+        dlx.add_row(&[2, 4, 5]);
+        dlx.add_row(&[0, 3, 6]);
+        dlx.add_row(&[1, 2, 5]);
+        dlx.add_row(&[0, 3]);
+        dlx.add_row(&[1, 6]);
+        dlx.add_row(&[3, 4, 6]);
+        dlx
+    }
+
+    // ranks and files are primary, diagonals are secondary since not all of
+    // them get a queen
+    fn queens(n: usize) -> Dlx {
+        let mut dlx = Dlx::new(2 * n, 2 * (2 * n - 1));
+        for y in 0..n {
+            for x in 0..n {
+                dlx.add_row(&[
+                    y,
+                    n + x,
+                    2 * n + y + x,
+                    2 * n + (2 * n - 1) + (n - 1 + y - x),
+                ]);
+            }
+        }
+        dlx
+    }
+
+    #[test]
+    fn knuth_example() {
+        let mut dlx = knuth();
+        assert_eq!(dlx.first(), Some(vec![0, 3, 4]));
+        assert_eq!(dlx.count(), 1);
+        // searching leaves the links as they were
+        assert_eq!(dlx.all(), vec![vec![0, 3, 4]]);
+    }
+
+    #[test]
+    fn no_solution() {
+        let mut dlx = Dlx::new(3, 0);
+        dlx.add_row(&[0, 1]);
+        dlx.add_row(&[1, 2]);
+        assert_eq!(dlx.first(), None);
+        assert_eq!(dlx.count(), 0);
+    }
+
+    #[test]
+    fn n_queens() {
+        assert_eq!(queens(4).all(), vec![vec![1, 7, 8, 14], vec![2, 4, 11, 13]]);
+        assert_eq!(queens(6).count(), 4);
+        assert_eq!(queens(8).count(), 92);
+    }
+
+    #[test]
+    fn step_limit() {
+        let outcome = queens(8).with_step_limit(50).solve(Mode::Count);
+        assert!(outcome.limit_hit);
+        assert_eq!(outcome.steps, 50);
+        assert!(outcome.count < 92);
+
+        let outcome = queens(8).solve(Mode::Count);
+        assert!(!outcome.limit_hit);
+        assert_eq!(outcome.count, 92);
+    }
+}
@@ -0,0 +1,305 @@
+// exact geometry on integer rectilinear polygons, so every edge is horizontal
+// or vertical. points are (x, y)
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Location {
+    Inside,
+    Boundary,
+    Outside,
+}
+
+#[derive(Debug, Clone)]
+pub struct Polygon {
+    vertices: Vec<(i64, i64)>,
+}
+
+impl Polygon {
+    // the vertices in order around the polygon, it gets closed automatically
+    pub fn new(vertices: Vec<(i64, i64)>) -> Self {
+        assert!(vertices.len() >= 4, "a rectilinear polygon needs 4 corners");
+        let polygon = Polygon { vertices };
+        for (a, b) in polygon.edges() {
+            assert!(
+                (a.0 == b.0) != (a.1 == b.1),
+                "edge {a:?} -> {b:?} isn't horizontal or vertical"
+            );
+        }
+        polygon
+    }
+
+    pub fn vertices(&self) -> &[(i64, i64)] {
+        &self.vertices
+    }
+
+    pub fn edges(&self) -> impl Iterator<Item = ((i64, i64), (i64, i64))> + '_ {
+        self.vertices
+            .iter()
+            .copied()
+            .zip(self.vertices.iter().copied().cycle().skip(1))
+    }
+
+    // shoelace formula. positive if the vertices go anticlockwise (with y
+    // going up)
+    pub fn double_signed_area(&self) -> i64 {
+        self.edges().map(|(a, b)| a.0 * b.1 - b.0 * a.1).sum()
+    }
+
+    // always a whole number since the corners are all right angles
+    pub fn area(&self) -> i64 {
+        self.double_signed_area().abs() / 2
+    }
+
+    // how many lattice points are on the edges
+    pub fn boundary_points(&self) -> i64 {
+        self.edges()
+            .map(|(a, b)| (a.0 - b.0).abs() + (a.1 - b.1).abs())
+            .sum()
+    }
+
+    // how many lattice points are inside or on the edges, by pick's theorem
+    // (area = inside + boundary / 2 - 1)
+    pub fn lattice_points(&self) -> i64 {
+        self.area() + self.boundary_points() / 2 + 1
+    }
+
+    pub fn locate(&self, point: (i64, i64)) -> Location {
+        locate(self.edges(), point)
+    }
+
+    // inside or on the boundary
+    pub fn contains(&self, point: (i64, i64)) -> bool {
+        self.locate(point) != Location::Outside
+    }
+
+    pub fn index(&self) -> RectIndex {
+        RectIndex::new(self)
+    }
+}
+
+fn locate(edges: impl Iterator<Item = ((i64, i64), (i64, i64))>, (px, py): (i64, i64)) -> Location {
+    // a ray going right crosses the boundary an odd number of times if the
+    // point is inside. vertical edges count for y_min <= y < y_max, which
+    // takes care of the ray going exactly through a corner
+    let mut crossings = 0;
+    for (a, b) in edges {
+        let (x_min, x_max) = (a.0.min(b.0), a.0.max(b.0));
+        let (y_min, y_max) = (a.1.min(b.1), a.1.max(b.1));
+        if (x_min..=x_max).contains(&px) && (y_min..=y_max).contains(&py) {
+            return Location::Boundary;
+        }
+        if a.0 == b.0 && a.0 > px && y_min <= py && py < y_max {
+            crossings += 1;
+        }
+    }
+    if crossings % 2 == 1 {
+        Location::Inside
+    } else {
+        Location::Outside
+    }
+}
+
+// answers whether every lattice point of a rectangle is inside or on a
+// polygon in O(1). the x coordinates of the vertices split the plane into
+// columns, alternating between a single coordinate and the open gap up to the
+// next one, and the same goes for y. every cell of that grid is all inside or
+// all outside, so a prefix sum of outside cells is all it takes
+pub struct RectIndex {
+    xs: Vec<i64>,
+    ys: Vec<i64>,
+    // outside[r][c] = outside cells with a row < r and column < c
+    outside: Vec<Vec<u32>>,
+}
+
+// which column (or row) a coordinate falls in, if any
+fn compressed(coords: &[i64], v: i64) -> Option<usize> {
+    let i = coords.partition_point(|&c| c < v);
+    match coords.get(i) {
+        Some(&c) if c == v => Some(2 * i),
+        Some(_) if i > 0 => Some(2 * i - 1),
+        _ => None,
+    }
+}
+
+impl RectIndex {
+    fn new(polygon: &Polygon) -> Self {
+        let mut xs = polygon.vertices.iter().map(|v| v.0).collect::<Vec<_>>();
+        let mut ys = polygon.vertices.iter().map(|v| v.1).collect::<Vec<_>>();
+        xs.sort_unstable();
+        xs.dedup();
+        ys.sort_unstable();
+        ys.dedup();
+        let (cols, rows) = (2 * xs.len() - 1, 2 * ys.len() - 1);
+
+        // the edges themselves, and for every row which columns a ray going
+        // right crosses (the same rule as locate uses)
+        let mut inside = vec![vec![false; cols]; rows];
+        let mut crossings = vec![Vec::new(); rows];
+        for (a, b) in polygon.edges() {
+            let (ca, cb) = (compressed(&xs, a.0).unwrap(), compressed(&xs, b.0).unwrap());
+            let (ra, rb) = (compressed(&ys, a.1).unwrap(), compressed(&ys, b.1).unwrap());
+            for row in &mut inside[ra.min(rb)..=ra.max(rb)] {
+                row[ca.min(cb)..=ca.max(cb)].fill(true);
+            }
+            if ca == cb {
+                for row in &mut crossings[ra.min(rb)..ra.max(rb)] {
+                    row.push(ca);
+                }
+            }
+        }
+        for (row, crossings) in inside.iter_mut().zip(&mut crossings) {
+            crossings.sort_unstable();
+            for pair in crossings.chunks(2) {
+                row[pair[0]..=pair[1]].fill(true);
+            }
+        }
+
+        // gaps between coordinates that are next to each other have no lattice
+        // points in them, so they never count as outside
+        let has_points =
+            |coords: &[i64], i: usize| i.is_multiple_of(2) || coords[i / 2 + 1] - coords[i / 2] > 1;
+        let mut outside = vec![vec![0; cols + 1]; rows + 1];
+        for r in 0..rows {
+            for c in 0..cols {
+                let cell = !inside[r][c] && has_points(&ys, r) && has_points(&xs, c);
+                outside[r + 1][c + 1] =
+                    outside[r][c + 1] + outside[r + 1][c] - outside[r][c] + cell as u32;
+            }
+        }
+        RectIndex { xs, ys, outside }
+    }
+
+    // a and b are opposite corners, and the rectangle includes its edges
+    pub fn contains_rect(&self, a: (i64, i64), b: (i64, i64)) -> bool {
+        let columns = compressed(&self.xs, a.0.min(b.0)).zip(compressed(&self.xs, a.0.max(b.0)));
+        let rows = compressed(&self.ys, a.1.min(b.1)).zip(compressed(&self.ys, a.1.max(b.1)));
+        let (Some((c0, c1)), Some((r0, r1))) = (columns, rows) else {
+            // sticks out of the bounding box
+            return false;
+        };
+        let o = &self.outside;
+        o[r1 + 1][c1 + 1] + o[r0][c0] - o[r0][c1 + 1] - o[r1 + 1][c0] == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // .#X#............#X#.
+    // .XXX............XXX.
+    // .XXX............XXX.
+    // .XXX............XXX.
+    // .XXX............XXX.
+    // .XXX............XXX.
+    // .XX#XXXXXXXXXXXX#XX.
+    // .XXXXX#XXXXXX#XXXXX.
+    // .XXXXXX......XXXXXX.
+    // .#XXXX#......#XXXX#.
+    fn u_shape() -> Polygon {
+        Polygon::new(vec![
+            (1, 0),
+            (3, 0),
+            (3, 6),
+            (16, 6),
+            (16, 0),
+            (18, 0),
+            (18, 9),
+            (13, 9),
+            (13, 7),
+            (6, 7),
+            (6, 9),
+            (1, 9),
+        ])
+    }
+
+    #[test]
+    fn area() {
+        let square = Polygon::new(vec![(0, 0), (4, 0), (4, 4), (0, 4)]);
+        assert_eq!(square.double_signed_area(), 32);
+        assert_eq!(square.area(), 16);
+        assert_eq!(square.boundary_points(), 16);
+        assert_eq!(square.lattice_points(), 25);
+
+        let reversed = Polygon::new(vec![(0, 4), (4, 4), (4, 0), (0, 0)]);
+        assert_eq!(reversed.double_signed_area(), -32);
+
+        let polygon = u_shape();
+        let brute = (0..20)
+            .flat_map(|x| (0..10).map(move |y| (x, y)))
+            .filter(|&p| polygon.contains(p))
+            .count();
+        assert_eq!(polygon.lattice_points(), brute as i64);
+    }
+
+    #[test]
+    fn point_location() {
+        let polygon = u_shape();
+        assert_eq!(polygon.locate((2, 3)), Location::Inside);
+        assert_eq!(polygon.locate((3, 3)), Location::Boundary);
+        assert_eq!(polygon.locate((1, 0)), Location::Boundary);
+        assert_eq!(polygon.locate((8, 3)), Location::Outside);
+        assert_eq!(polygon.locate((8, 8)), Location::Outside);
+        // the ray from here goes straight along the top edges
+        assert_eq!(polygon.locate((0, 0)), Location::Outside);
+        assert_eq!(polygon.locate((0, 6)), Location::Outside);
+        assert_eq!(polygon.locate((10, 6)), Location::Boundary);
+    }
+
+    #[test]
+    fn rectangles() {
+        let polygon = u_shape();
+        let index = polygon.index();
+        assert!(index.contains_rect((1, 0), (3, 9)));
+        assert!(index.contains_rect((1, 6), (18, 7)));
+        assert!(!index.contains_rect((18, 9), (1, 8)));
+        assert!(!index.contains_rect((1, 0), (6, 9)));
+        assert!(!index.contains_rect((0, 0), (1, 1)));
+        assert!(index.contains_rect((2, 2), (2, 2)));
+        assert!(!index.contains_rect((8, 2), (8, 2)));
+
+        // every rectangle agrees with checking each point
+        for (a, b) in [(1, 0), (0, 3), (17, 9), (6, 7), (12, 5), (19, 8)]
+            .into_iter()
+            .flat_map(|a| [(3, 6), (16, 8), (2, 9), (13, 0)].map(|b| (a, b)))
+        {
+            let brute = (a.0.min(b.0)..=a.0.max(b.0))
+                .all(|x| (a.1.min(b.1)..=a.1.max(b.1)).all(|y| polygon.contains((x, y))));
+            assert_eq!(index.contains_rect(a, b), brute, "{a:?} {b:?}");
+        }
+    }
+
+    #[test]
+    fn touching_edges() {
+        // #X.X#
+        // #XXX#
+        // #####
+        let polygon = Polygon::new(vec![
+            (0, 0),
+            (1, 0),
+            (1, 1),
+            (3, 1),
+            (3, 0),
+            (4, 0),
+            (4, 2),
+            (0, 2),
+        ]);
+        let index = polygon.index();
+        assert!(!index.contains_rect((0, 0), (4, 2)));
+        let polygon = Polygon::new(vec![
+            (0, 0),
+            (1, 0),
+            (1, 1),
+            (2, 1),
+            (2, 0),
+            (3, 0),
+            (3, 2),
+            (0, 2),
+        ]);
+        // the same notch, but only one wide. the edges at x = 1 and x = 2 are
+        // next to each other so there's no tile in between to be outside
+        // ##X#
+        // #XX#
+        // ####
+        assert!(polygon.index().contains_rect((0, 0), (3, 2)));
+    }
+}
@@ -0,0 +1,450 @@
+// undirected graphs with named vertices, for finding cliques. names get
+// turned into dense ids and neighbours are stored as bitsets, so intersecting
+// candidate sets is a few word ands
+
+use super::Interner;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Bitset {
+    words: Vec<u64>,
+}
+
+impl Bitset {
+    fn new(len: usize) -> Self {
+        Bitset {
+            words: vec![0; len.div_ceil(64)],
+        }
+    }
+
+    fn resize(&mut self, len: usize) {
+        self.words.resize(len.div_ceil(64), 0);
+    }
+
+    fn insert(&mut self, i: usize) {
+        self.words[i / 64] |= 1 << (i % 64);
+    }
+
+    fn remove(&mut self, i: usize) {
+        self.words[i / 64] &= !(1 << (i % 64));
+    }
+
+    fn contains(&self, i: usize) -> bool {
+        self.words[i / 64] >> (i % 64) & 1 == 1
+    }
+
+    fn and(&self, other: &Bitset) -> Bitset {
+        Bitset {
+            words: self
+                .words
+                .iter()
+                .zip(&other.words)
+                .map(|(a, b)| a & b)
+                .collect(),
+        }
+    }
+
+    fn and_not(&self, other: &Bitset) -> Bitset {
+        Bitset {
+            words: self
+                .words
+                .iter()
+                .zip(&other.words)
+                .map(|(a, b)| a & !b)
+                .collect(),
+        }
+    }
+
+    fn or(&self, other: &Bitset) -> Bitset {
+        Bitset {
+            words: self
+                .words
+                .iter()
+                .zip(&other.words)
+                .map(|(a, b)| a | b)
+                .collect(),
+        }
+    }
+
+    fn count(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.words.iter().all(|&w| w == 0)
+    }
+
+    fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(i, &word)| {
+            let mut word = word;
+            std::iter::from_fn(move || {
+                (word != 0).then(|| {
+                    let bit = word.trailing_zeros() as usize;
+                    word &= word - 1;
+                    i * 64 + bit
+                })
+            })
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Graph<'a> {
+    names: Interner<'a>,
+    neighbours: Vec<Bitset>,
+}
+
+impl<'a> Graph<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_edges(edges: impl IntoIterator<Item = (&'a str, &'a str)>) -> Self {
+        let mut graph = Graph::new();
+        for (a, b) in edges {
+            graph.add_edge(a, b);
+        }
+        graph
+    }
+
+    // returns the id, adding the vertex if it's new
+    pub fn add_vertex(&mut self, name: &'a str) -> usize {
+        let id = self.names.intern(name) as usize;
+        if id == self.neighbours.len() {
+            self.neighbours.push(Bitset::new(0));
+            for neighbours in &mut self.neighbours {
+                neighbours.resize(id + 1);
+            }
+        }
+        id
+    }
+
+    pub fn add_edge(&mut self, a: &'a str, b: &'a str) {
+        let (a, b) = (self.add_vertex(a), self.add_vertex(b));
+        assert_ne!(a, b, "no loops allowed");
+        self.neighbours[a].insert(b);
+        self.neighbours[b].insert(a);
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    pub fn id(&self, name: &str) -> Option<usize> {
+        self.names.get(name).map(|id| id as usize)
+    }
+
+    pub fn name(&self, id: usize) -> &'a str {
+        self.names.label(id as u32)
+    }
+
+    pub fn degree(&self, id: usize) -> usize {
+        self.neighbours[id].count()
+    }
+
+    pub fn neighbours(&self, id: usize) -> impl Iterator<Item = usize> + '_ {
+        self.neighbours[id].iter()
+    }
+
+    pub fn is_edge(&self, a: usize, b: usize) -> bool {
+        self.neighbours[a].contains(b)
+    }
+
+    // the names of a set of vertices, sorted
+    pub fn names(&self, ids: &[usize]) -> Vec<&'a str> {
+        let mut names = ids.iter().map(|&id| self.name(id)).collect::<Vec<_>>();
+        names.sort_unstable();
+        names
+    }
+
+    // keeps taking out the vertex with the smallest degree that's left. every
+    // vertex has at most degeneracy neighbours later in the order, which keeps
+    // the searches below small on sparse graphs
+    pub fn degeneracy_order(&self) -> Vec<usize> {
+        let mut degree = (0..self.len()).map(|v| self.degree(v)).collect::<Vec<_>>();
+        // buckets by degree, entries go stale when the degree drops
+        let mut buckets = vec![Vec::new(); self.len()];
+        for (v, &d) in degree.iter().enumerate() {
+            buckets[d].push(v);
+        }
+        let mut removed = Bitset::new(self.len());
+        let mut order = Vec::with_capacity(self.len());
+        let mut lowest = 0;
+        while order.len() < self.len() {
+            let Some(v) = buckets[lowest].pop() else {
+                lowest += 1;
+                continue;
+            };
+            if removed.contains(v) || degree[v] != lowest {
+                continue;
+            }
+            removed.insert(v);
+            order.push(v);
+            for w in self.neighbours[v].and_not(&removed).iter() {
+                degree[w] -= 1;
+                buckets[degree[w]].push(w);
+            }
+            lowest = lowest.saturating_sub(1);
+        }
+        order
+    }
+
+    // for every vertex, its neighbours that come after it in the degeneracy
+    // order
+    fn later_neighbours(&self) -> (Vec<usize>, Vec<Bitset>) {
+        let order = self.degeneracy_order();
+        let mut later = vec![Bitset::new(self.len()); self.len()];
+        let mut seen = Bitset::new(self.len());
+        for &v in order.iter().rev() {
+            later[v] = self.neighbours[v].and(&seen);
+            seen.insert(v);
+        }
+        (order, later)
+    }
+
+    // every clique with exactly k vertices, each one sorted by id
+    pub fn k_cliques(&self, k: usize) -> Vec<Vec<usize>> {
+        let (_, later) = self.later_neighbours();
+        let mut all = Bitset::new(self.len());
+        (0..self.len()).for_each(|v| all.insert(v));
+
+        // cliques only grow with vertices later in the order, so each one is
+        // found exactly once
+        fn grow(
+            k: usize,
+            later: &[Bitset],
+            clique: &mut Vec<usize>,
+            candidates: &Bitset,
+            out: &mut Vec<Vec<usize>>,
+        ) {
+            if clique.len() == k {
+                let mut found = clique.clone();
+                found.sort_unstable();
+                out.push(found);
+                return;
+            }
+            if clique.len() + candidates.count() < k {
+                return;
+            }
+            for v in candidates.iter() {
+                clique.push(v);
+                grow(k, later, clique, &candidates.and(&later[v]), out);
+                clique.pop();
+            }
+        }
+
+        let mut out = Vec::new();
+        grow(k, &later, &mut Vec::new(), &all, &mut out);
+        out
+    }
+
+    // every clique that can't be made any bigger, each one sorted by id
+    pub fn maximal_cliques(&self) -> Vec<Vec<usize>> {
+        let mut out = Vec::new();
+        self.each_maximal_clique(|clique| {
+            let mut clique = clique.to_vec();
+            clique.sort_unstable();
+            out.push(clique);
+        });
+        out
+    }
+
+    // the biggest clique, sorted by id. if there's a tie it's whichever got
+    // found first
+    pub fn maximum_clique(&self) -> Vec<usize> {
+        let mut best = Vec::new();
+        self.each_maximal_clique(|clique| {
+            if clique.len() > best.len() {
+                best = clique.to_vec();
+            }
+        });
+        best.sort_unstable();
+        best
+    }
+
+    // bron-kerbosch, started from each vertex in degeneracy order with only
+    // the later neighbours as candidates and the earlier ones as excluded
+    fn each_maximal_clique(&self, mut found: impl FnMut(&[usize])) {
+        let (order, later) = self.later_neighbours();
+        for v in order {
+            let excluded = self.neighbours[v].and_not(&later[v]);
+            self.bron_kerbosch(&mut vec![v], later[v].clone(), excluded, &mut found);
+        }
+    }
+
+    fn bron_kerbosch(
+        &self,
+        clique: &mut Vec<usize>,
+        mut candidates: Bitset,
+        mut excluded: Bitset,
+        found: &mut impl FnMut(&[usize]),
+    ) {
+        if candidates.is_empty() {
+            if excluded.is_empty() {
+                found(clique);
+            }
+            return;
+        }
+        // any maximal clique has to include the pivot or one of its
+        // non-neighbours, so there's no point branching on its neighbours.
+        // picking the one with the most candidate neighbours skips the most
+        let pivot = candidates
+            .or(&excluded)
+            .iter()
+            .max_by_key(|&u| candidates.and(&self.neighbours[u]).count())
+            .unwrap();
+        for v in candidates
+            .and_not(&self.neighbours[pivot])
+            .iter()
+            .collect::<Vec<_>>()
+        {
+            clique.push(v);
+            self.bron_kerbosch(
+                clique,
+                candidates.and(&self.neighbours[v]),
+                excluded.and(&self.neighbours[v]),
+                found,
+            );
+            clique.pop();
+            candidates.remove(v);
+            excluded.insert(v);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::TestRng;
+
+    // a random graph with n vertices named by number
+    fn random(n: usize, density: u64, seed: u64) -> (Vec<String>, Vec<(usize, usize)>) {
+        let mut rng = TestRng::new(seed);
+        let names = (0..n).map(|i| i.to_string()).collect::<Vec<_>>();
+        let mut edges = Vec::new();
+        for a in 0..n {
+            for b in a + 1..n {
+                if rng.below(100) < density {
+                    edges.push((a, b));
+                }
+            }
+        }
+        (names, edges)
+    }
+
+    fn is_clique(graph: &Graph, vertices: &[usize]) -> bool {
+        vertices
+            .iter()
+            .enumerate()
+            .all(|(i, &a)| vertices[i + 1..].iter().all(|&b| graph.is_edge(a, b)))
+    }
+
+    #[test]
+    fn small() {
+        // two triangles sharing an edge, and a square with one diagonal
+        let graph = Graph::from_edges([
+            ("a", "b"),
+            ("b", "c"),
+            ("c", "a"),
+            ("c", "d"),
+            ("d", "a"),
+            ("e", "f"),
+        ]);
+        let named = |cliques: Vec<Vec<usize>>| {
+            let mut named = cliques
+                .iter()
+                .map(|clique| graph.names(clique).concat())
+                .collect::<Vec<_>>();
+            named.sort();
+            named
+        };
+        assert_eq!(named(graph.k_cliques(3)), vec!["abc", "acd"]);
+        assert_eq!(named(graph.k_cliques(2)).len(), 6);
+        assert_eq!(named(graph.maximal_cliques()), vec!["abc", "acd", "ef"]);
+        assert_eq!(graph.maximum_clique().len(), 3);
+        assert!(graph.k_cliques(4).is_empty());
+        assert_eq!(graph.k_cliques(0), vec![Vec::<usize>::new()]);
+    }
+
+    #[test]
+    fn degeneracy() {
+        // a 4-clique with a tail hanging off it
+        let graph = Graph::from_edges([
+            ("a", "b"),
+            ("a", "c"),
+            ("a", "d"),
+            ("b", "c"),
+            ("b", "d"),
+            ("c", "d"),
+            ("d", "e"),
+            ("e", "f"),
+        ]);
+        let order = graph.degeneracy_order();
+        let rank = |name| order.iter().position(|&v| v == graph.id(name).unwrap());
+        assert_eq!(rank("f"), Some(0));
+        assert_eq!(rank("e"), Some(1));
+        // nothing has more than 3 neighbours later on
+        for (i, &v) in order.iter().enumerate() {
+            let later = graph
+                .neighbours(v)
+                .filter(|w| order[i..].contains(w))
+                .count();
+            assert!(later <= 3);
+        }
+    }
+
+    #[test]
+    fn against_brute_force() {
+        for (seed, density) in [(1, 30), (2, 50), (3, 70), (4, 90)] {
+            let n = 12;
+            let (names, edges) = random(n, density, seed);
+            let mut graph = Graph::new();
+            for name in &names {
+                graph.add_vertex(name);
+            }
+            for &(a, b) in &edges {
+                graph.add_edge(&names[a], &names[b]);
+            }
+
+            let cliques = (0u32..1 << n)
+                .map(|mask| (0..n).filter(|&v| mask >> v & 1 == 1).collect::<Vec<_>>())
+                .filter(|vertices| is_clique(&graph, vertices))
+                .collect::<Vec<_>>();
+            let maximal = cliques
+                .iter()
+                .filter(|clique| {
+                    (0..n).all(|v| {
+                        clique.contains(&v)
+                            || !is_clique(&graph, &[clique.as_slice(), &[v]].concat())
+                    })
+                })
+                .cloned()
+                .collect::<Vec<_>>();
+            let biggest = cliques.iter().map(Vec::len).max().unwrap();
+
+            for k in 0..=biggest + 1 {
+                let mut found = graph.k_cliques(k);
+                found.sort();
+                let mut expected = cliques
+                    .iter()
+                    .filter(|c| c.len() == k)
+                    .cloned()
+                    .collect::<Vec<_>>();
+                expected.sort();
+                assert_eq!(found, expected, "{k}-cliques with seed {seed}");
+            }
+            let mut found = graph.maximal_cliques();
+            found.sort();
+            let mut expected = maximal;
+            expected.sort();
+            assert_eq!(found, expected, "maximal cliques with seed {seed}");
+
+            let maximum = graph.maximum_clique();
+            assert_eq!(maximum.len(), biggest);
+            assert!(is_clique(&graph, &maximum));
+        }
+    }
+}
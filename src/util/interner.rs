@@ -0,0 +1,62 @@
+use rustc_hash::FxHashMap;
+
+// hands out dense ids for labels like node names, in the order they're first
+// seen, so they can index into vecs instead of hashing strings everywhere
+#[derive(Debug, Clone, Default)]
+pub struct Interner<'a> {
+    labels: Vec<&'a str>,
+    ids: FxHashMap<&'a str, u32>,
+}
+
+impl<'a> Interner<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // the id of the label, giving it the next one if it's new
+    pub fn intern(&mut self, label: &'a str) -> u32 {
+        *self.ids.entry(label).or_insert_with(|| {
+            self.labels.push(label);
+            (self.labels.len() - 1) as u32
+        })
+    }
+
+    pub fn get(&self, label: &str) -> Option<u32> {
+        self.ids.get(label).copied()
+    }
+
+    pub fn label(&self, id: u32) -> &'a str {
+        self.labels[id as usize]
+    }
+
+    // indexed by id
+    pub fn labels(&self) -> &[&'a str] {
+        &self.labels
+    }
+
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut interner = Interner::new();
+        assert!(interner.is_empty());
+        let ids = ["kh", "z45", "svr", "kh", "z45"].map(|label| interner.intern(label));
+        assert_eq!(ids, [0, 1, 2, 0, 1]);
+        assert_eq!(interner.len(), 3);
+        assert_eq!(interner.get("svr"), Some(2));
+        assert_eq!(interner.get("out"), None);
+        assert_eq!(interner.label(1), "z45");
+        assert_eq!(interner.labels(), ["kh", "z45", "svr"]);
+    }
+}
@@ -0,0 +1,505 @@
+use std::{
+    cmp::Ordering,
+    fmt::{self, Display},
+    ops::{Add, Div, Index, IndexMut, Mul, Neg, Sub},
+};
+
+const OVERFLOW: &str = "rational arithmetic overflowed i128";
+
+fn gcd(mut a: i128, mut b: i128) -> i128 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a.abs()
+}
+
+// an exact fraction. always kept in lowest terms with a positive denominator, so
+// the derived equality works
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Rational {
+    num: i128,
+    den: i128,
+}
+
+impl Rational {
+    pub const ZERO: Rational = Rational { num: 0, den: 1 };
+    pub const ONE: Rational = Rational { num: 1, den: 1 };
+
+    pub fn new(num: i128, den: i128) -> Self {
+        assert_ne!(den, 0, "rational with a zero denominator");
+        let g = gcd(num, den);
+        let sign = den.signum();
+        Rational {
+            num: sign * num / g,
+            den: sign * den / g,
+        }
+    }
+
+    pub fn numer(self) -> i128 {
+        self.num
+    }
+
+    pub fn denom(self) -> i128 {
+        self.den
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.num == 0
+    }
+
+    pub fn is_integer(self) -> bool {
+        self.den == 1
+    }
+
+    pub fn to_integer(self) -> Option<i128> {
+        self.is_integer().then_some(self.num)
+    }
+
+    pub fn floor(self) -> i128 {
+        self.num.div_euclid(self.den)
+    }
+
+    pub fn ceil(self) -> i128 {
+        -(-self).floor()
+    }
+
+    pub fn abs(self) -> Self {
+        Rational {
+            num: self.num.abs(),
+            den: self.den,
+        }
+    }
+
+    pub fn recip(self) -> Self {
+        Rational::new(self.den, self.num)
+    }
+
+    // self + num / den
+    fn add_parts(self, num: i128, den: i128) -> Self {
+        let g = gcd(self.den, den);
+        let num = (self.num.checked_mul(den / g))
+            .and_then(|a| a.checked_add(num.checked_mul(self.den / g)?))
+            .expect(OVERFLOW);
+        let den = (self.den / g).checked_mul(den).expect(OVERFLOW);
+        Rational::new(num, den)
+    }
+
+    // self * num / den
+    fn mul_parts(self, num: i128, den: i128) -> Self {
+        // cross-reduce first to keep the intermediate values small
+        let g1 = gcd(self.num, den).max(1);
+        let g2 = gcd(num, self.den).max(1);
+        let num = (self.num / g1).checked_mul(num / g2).expect(OVERFLOW);
+        let den = (self.den / g2).checked_mul(den / g1).expect(OVERFLOW);
+        Rational::new(num, den)
+    }
+}
+
+impl From<i32> for Rational {
+    fn from(n: i32) -> Self {
+        Rational::from(i128::from(n))
+    }
+}
+
+impl From<i64> for Rational {
+    fn from(n: i64) -> Self {
+        Rational::from(i128::from(n))
+    }
+}
+
+impl From<i128> for Rational {
+    fn from(n: i128) -> Self {
+        Rational { num: n, den: 1 }
+    }
+}
+
+impl Add for Rational {
+    type Output = Rational;
+    fn add(self, rhs: Rational) -> Rational {
+        self.add_parts(rhs.num, rhs.den)
+    }
+}
+
+impl Neg for Rational {
+    type Output = Rational;
+    fn neg(self) -> Rational {
+        Rational {
+            num: -self.num,
+            den: self.den,
+        }
+    }
+}
+
+impl Sub for Rational {
+    type Output = Rational;
+    fn sub(self, rhs: Rational) -> Rational {
+        self.add_parts(-rhs.num, rhs.den)
+    }
+}
+
+impl Mul for Rational {
+    type Output = Rational;
+    fn mul(self, rhs: Rational) -> Rational {
+        self.mul_parts(rhs.num, rhs.den)
+    }
+}
+
+impl Div for Rational {
+    type Output = Rational;
+    fn div(self, rhs: Rational) -> Rational {
+        assert_ne!(rhs.num, 0, "rational division by zero");
+        self.mul_parts(rhs.den, rhs.num)
+    }
+}
+
+impl Ord for Rational {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // denominators are positive so cross-multiplying keeps the order
+        let lhs = self.num.checked_mul(other.den).expect(OVERFLOW);
+        let rhs = other.num.checked_mul(self.den).expect(OVERFLOW);
+        lhs.cmp(&rhs)
+    }
+}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Display for Rational {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.den == 1 {
+            write!(f, "{}", self.num)
+        } else {
+            write!(f, "{}/{}", self.num, self.den)
+        }
+    }
+}
+
+// returns Some only if every value is an integer
+pub fn to_integers(values: &[Rational]) -> Option<Vec<i128>> {
+    values.iter().map(|v| v.to_integer()).collect()
+}
+
+// a dense row-major matrix of rationals
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Matrix {
+    rows: usize,
+    cols: usize,
+    data: Vec<Rational>,
+}
+
+impl Matrix {
+    pub fn zeros(rows: usize, cols: usize) -> Self {
+        Matrix {
+            rows,
+            cols,
+            data: vec![Rational::ZERO; rows * cols],
+        }
+    }
+
+    pub fn identity(n: usize) -> Self {
+        let mut m = Matrix::zeros(n, n);
+        for i in 0..n {
+            m[(i, i)] = Rational::ONE;
+        }
+        m
+    }
+
+    pub fn from_rows<R, T>(rows: impl IntoIterator<Item = R>) -> Self
+    where
+        R: IntoIterator<Item = T>,
+        T: Into<Rational>,
+    {
+        let mut data = vec![];
+        let mut row_count = 0;
+        let mut cols = None;
+        for row in rows {
+            let before = data.len();
+            data.extend(row.into_iter().map(Into::into));
+            let len = data.len() - before;
+            assert_eq!(*cols.get_or_insert(len), len, "rows have different lengths");
+            row_count += 1;
+        }
+        Matrix {
+            rows: row_count,
+            cols: cols.unwrap_or(0),
+            data,
+        }
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn row(&self, y: usize) -> &[Rational] {
+        &self.data[y * self.cols..(y + 1) * self.cols]
+    }
+
+    fn swap_rows(&mut self, a: usize, b: usize) {
+        for x in 0..self.cols {
+            self.data.swap(a * self.cols + x, b * self.cols + x);
+        }
+    }
+
+    // matrix with `column` appended as the last column
+    pub fn augment(&self, column: &[Rational]) -> Self {
+        assert_eq!(column.len(), self.rows);
+        Matrix::from_rows((0..self.rows).map(|y| self.row(y).iter().copied().chain([column[y]])))
+    }
+
+    // reduced row echelon form, and the indices of the pivot columns. same
+    // output as sympy's Matrix.rref()
+    pub fn rref(&self) -> (Matrix, Vec<usize>) {
+        let mut m = self.clone();
+        let mut pivots = vec![];
+        let mut pivot_row = 0;
+        for col in 0..m.cols {
+            if pivot_row == m.rows {
+                break;
+            }
+            let Some(y) = (pivot_row..m.rows).find(|&y| !m[(y, col)].is_zero()) else {
+                continue;
+            };
+            m.swap_rows(pivot_row, y);
+
+            let scale = m[(pivot_row, col)].recip();
+            for x in col..m.cols {
+                m[(pivot_row, x)] = m[(pivot_row, x)] * scale;
+            }
+            for y in (0..m.rows).filter(|&y| y != pivot_row) {
+                let factor = m[(y, col)];
+                if factor.is_zero() {
+                    continue;
+                }
+                for x in col..m.cols {
+                    m[(y, x)] = m[(y, x)] - factor * m[(pivot_row, x)];
+                }
+            }
+
+            pivots.push(col);
+            pivot_row += 1;
+        }
+        (m, pivots)
+    }
+
+    pub fn rank(&self) -> usize {
+        self.rref().1.len()
+    }
+
+    pub fn determinant(&self) -> Rational {
+        assert_eq!(self.rows, self.cols, "determinant of a non-square matrix");
+        // plain elimination into an upper triangular matrix, where the
+        // determinant is the product of the diagonal
+        let mut m = self.clone();
+        let mut det = Rational::ONE;
+        for col in 0..m.cols {
+            let Some(y) = (col..m.rows).find(|&y| !m[(y, col)].is_zero()) else {
+                return Rational::ZERO;
+            };
+            if y != col {
+                m.swap_rows(col, y);
+                det = -det;
+            }
+            let pivot = m[(col, col)];
+            det = det * pivot;
+            for y in col + 1..m.rows {
+                let factor = m[(y, col)] / pivot;
+                if factor.is_zero() {
+                    continue;
+                }
+                for x in col..m.cols {
+                    m[(y, x)] = m[(y, x)] - factor * m[(col, x)];
+                }
+            }
+        }
+        det
+    }
+
+    // some solution of self * x = b with all free variables set to 0, or None if
+    // there is none. every solution is this plus a combination of null_space()
+    pub fn solve(&self, b: &[Rational]) -> Option<Vec<Rational>> {
+        let (rref, pivots) = self.augment(b).rref();
+        if pivots.last() == Some(&self.cols) {
+            // a row of 0 = 1
+            return None;
+        }
+        let mut x = vec![Rational::ZERO; self.cols];
+        for (y, &col) in pivots.iter().enumerate() {
+            x[col] = rref[(y, self.cols)];
+        }
+        Some(x)
+    }
+
+    // like solve, but only if the solution is the only one
+    pub fn unique_solution(&self, b: &[Rational]) -> Option<Vec<Rational>> {
+        if self.rank() != self.cols {
+            return None;
+        }
+        self.solve(b)
+    }
+
+    // a basis for the vectors x where self * x = 0. there is one vector for
+    // each free variable
+    pub fn null_space(&self) -> Vec<Vec<Rational>> {
+        let (rref, pivots) = self.rref();
+        (0..self.cols)
+            .filter(|col| !pivots.contains(col))
+            .map(|free| {
+                let mut v = vec![Rational::ZERO; self.cols];
+                v[free] = Rational::ONE;
+                for (y, &col) in pivots.iter().enumerate() {
+                    v[col] = -rref[(y, free)];
+                }
+                v
+            })
+            .collect()
+    }
+
+    pub fn mul_vec(&self, v: &[Rational]) -> Vec<Rational> {
+        assert_eq!(v.len(), self.cols);
+        (0..self.rows)
+            .map(|y| {
+                self.row(y)
+                    .iter()
+                    .zip(v)
+                    .fold(Rational::ZERO, |acc, (&a, &b)| acc + a * b)
+            })
+            .collect()
+    }
+}
+
+impl Index<(usize, usize)> for Matrix {
+    type Output = Rational;
+    fn index(&self, (y, x): (usize, usize)) -> &Rational {
+        assert!(y < self.rows && x < self.cols);
+        &self.data[y * self.cols + x]
+    }
+}
+
+impl IndexMut<(usize, usize)> for Matrix {
+    fn index_mut(&mut self, (y, x): (usize, usize)) -> &mut Rational {
+        assert!(y < self.rows && x < self.cols);
+        &mut self.data[y * self.cols + x]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn r(num: i128, den: i128) -> Rational {
+        Rational::new(num, den)
+    }
+
+    fn ints(values: &[i128]) -> Vec<Rational> {
+        values.iter().map(|&v| Rational::from(v)).collect()
+    }
+
+    #[test]
+    fn rationals() {
+        assert_eq!(r(2, -4), r(-1, 2));
+        assert_eq!(r(1, 3) + r(1, 6), r(1, 2));
+        assert_eq!(r(1, 3) - r(1, 2), r(-1, 6));
+        assert_eq!(r(3, 4) * r(2, 9), r(1, 6));
+        assert_eq!(r(3, 4) / r(-3, 8), Rational::from(-2));
+        assert!(r(-1, 2) < r(1, 3));
+        assert_eq!((r(-7, 2).floor(), r(-7, 2).ceil()), (-4, -3));
+        assert_eq!(r(5, 1).to_integer(), Some(5));
+        assert_eq!(r(5, 2).to_integer(), None);
+        assert_eq!(r(-5, 2).to_string(), "-5/2");
+    }
+
+    #[test]
+    fn rref() {
+        // the examples from day-10-testing.py, checked with sympy
+        let m = Matrix::from_rows([
+            [1, 0, 1, 1, 0, 7],
+            [0, 0, 0, 1, 1, 5],
+            [1, 1, 0, 1, 1, 12],
+            [1, 1, 0, 0, 1, 7],
+            [1, 0, 0, 0, 1, 2],
+        ]);
+        let expected = Matrix::from_rows([
+            [1, 0, 0, 0, 0, 2],
+            [0, 1, 0, 0, 0, 5],
+            [0, 0, 1, 0, 0, 0],
+            [0, 0, 0, 1, 0, 5],
+            [0, 0, 0, 0, 1, 0],
+        ]);
+        assert_eq!(m.rref(), (expected, vec![0, 1, 2, 3, 4]));
+
+        let m = Matrix::from_rows([
+            [1, 1, 1, 0, 0, 10],
+            [0, 0, 0, 1, 1, 11],
+            [1, 0, 1, 1, 0, 11],
+            [1, 1, 0, 0, 0, 5],
+            [1, 1, 1, 0, 0, 10],
+            [0, 0, 1, 0, 0, 5],
+        ]);
+        let expected = Matrix::from_rows([
+            [1, 0, 0, 0, -1, -5],
+            [0, 1, 0, 0, 1, 10],
+            [0, 0, 1, 0, 0, 5],
+            [0, 0, 0, 1, 1, 11],
+            [0, 0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0, 0],
+        ]);
+        assert_eq!(m.rref(), (expected, vec![0, 1, 2, 3]));
+    }
+
+    #[test]
+    fn determinant() {
+        assert_eq!(
+            Matrix::from_rows([[2, -3, 1], [2, 0, -1], [1, 4, 5]]).determinant(),
+            Rational::from(49)
+        );
+        assert_eq!(
+            Matrix::from_rows([[0, 1], [1, 0]]).determinant(),
+            Rational::from(-1)
+        );
+        assert_eq!(
+            Matrix::from_rows([[1, 2], [2, 4]]).determinant(),
+            Rational::ZERO
+        );
+        assert_eq!(Matrix::identity(4).determinant(), Rational::ONE);
+    }
+
+    #[test]
+    fn solving() {
+        // 2024 day 13 style
+        let m = Matrix::from_rows([[94, 22], [34, 67]]);
+        let x = m.unique_solution(&ints(&[8400, 5400])).unwrap();
+        assert_eq!(to_integers(&x), Some(vec![80, 40]));
+        let x = m.unique_solution(&ints(&[8401, 5400])).unwrap();
+        assert_eq!(to_integers(&x), None);
+
+        // underdetermined, so the null space is needed for every solution
+        let m = Matrix::from_rows([[1, 1, 1, 0, 0], [0, 0, 0, 1, 1], [0, 0, 1, 0, 0]]);
+        let b = ints(&[10, 11, 5]);
+        assert_eq!(m.unique_solution(&b), None);
+        let x = m.solve(&b).unwrap();
+        assert_eq!(m.mul_vec(&x), b);
+        let null = m.null_space();
+        assert_eq!(null.len(), 2);
+        for v in &null {
+            assert!(m.mul_vec(v).iter().all(|c| c.is_zero()));
+        }
+        let combined = x
+            .iter()
+            .zip(&null[0])
+            .zip(&null[1])
+            .map(|((&a, &b), &c)| a + Rational::from(3) * b - Rational::from(2) * c)
+            .collect::<Vec<_>>();
+        assert_eq!(m.mul_vec(&combined), b);
+
+        // inconsistent
+        let m = Matrix::from_rows([[1, 1], [2, 2]]);
+        assert_eq!(m.solve(&ints(&[1, 3])), None);
+    }
+}
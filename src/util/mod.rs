@@ -0,0 +1,181 @@
+// general purpose, so not everything is used by the solvers
+#[allow(dead_code)]
+pub mod dlx;
+#[allow(dead_code)]
+pub mod geometry;
+#[allow(dead_code)]
+pub mod graph;
+#[allow(dead_code)]
+mod interner;
+#[allow(dead_code)]
+pub mod linalg;
+#[allow(dead_code)]
+mod range_set;
+pub mod render;
+#[cfg(test)]
+mod test_rng;
+mod union_find;
+
+pub use interner::Interner;
+pub use range_set::RangeSet;
+#[cfg(test)]
+pub use test_rng::TestRng;
+pub use union_find::UnionFind;
+
+pub fn adjacent_in_bounds(
+    y: usize,
+    x: usize,
+    matrix_len: usize,
+) -> impl Iterator<Item = (usize, usize)> {
+    const ADJACENTS: [(i32, i32); 4] = [(1, 0), (-1, 0), (0, -1), (0, 1)]; // up down left right
+    ADJACENTS.iter().filter_map(move |(dy, dx)| {
+        let pair = (y as i32 + *dy, x as i32 + *dx);
+        let legal_range = 0..(matrix_len as i32);
+        if legal_range.contains(&pair.0) && legal_range.contains(&pair.1) {
+            Some((pair.0 as usize, pair.1 as usize))
+        } else {
+            None
+        }
+    })
+}
+
+// like adjacent_in_bounds, but with diagonals too
+// returns iterator of (ny, nx)
+pub fn all_adjacent_in_bounds(
+    y: usize,
+    x: usize,
+    matrix_len: usize,
+) -> impl Iterator<Item = (usize, usize)> {
+    // L, R, T, B, TL, TR, BL, BR (dx, dy)
+    const ADJACENTS: [(i32, i32); 8] = [
+        (-1, 0),
+        (1, 0),
+        (0, -1),
+        (0, 1),
+        (-1, -1),
+        (1, -1),
+        (-1, 1),
+        (1, 1),
+    ];
+    ADJACENTS.iter().filter_map(move |(dy, dx)| {
+        let pair = (y as i32 + *dy, x as i32 + *dx);
+        let legal_range = 0..(matrix_len as i32);
+        if legal_range.contains(&pair.0) && legal_range.contains(&pair.1) {
+            Some((pair.0 as usize, pair.1 as usize))
+        } else {
+            None
+        }
+    })
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+impl Direction {
+    pub fn apply_unchecked(self, (y, x): (usize, usize)) -> (usize, usize) {
+        // no bounds checking required as the area is padded
+        match self {
+            Direction::Up => (y - 1, x),
+            Direction::Down => (y + 1, x),
+            Direction::Left => (y, x - 1),
+            Direction::Right => (y, x + 1),
+        }
+    }
+
+    // None if the step would leave a height x width grid
+    pub fn apply_checked(
+        self,
+        (y, x): (usize, usize),
+        height: usize,
+        width: usize,
+    ) -> Option<(usize, usize)> {
+        let (y, x) = match self {
+            Direction::Up => (y.checked_sub(1)?, x),
+            Direction::Down => (y + 1, x),
+            Direction::Left => (y, x.checked_sub(1)?),
+            Direction::Right => (y, x + 1),
+        };
+        (y < height && x < width).then_some((y, x))
+    }
+
+    pub const ALL: [Direction; 4] = [
+        Direction::Up,
+        Direction::Right,
+        Direction::Down,
+        Direction::Left,
+    ];
+
+    // position in ALL, for indexing arrays by direction
+    pub fn index(self) -> usize {
+        match self {
+            Direction::Up => 0,
+            Direction::Right => 1,
+            Direction::Down => 2,
+            Direction::Left => 3,
+        }
+    }
+
+    pub fn clockwise(self) -> Self {
+        Direction::ALL[(self.index() + 1) % 4]
+    }
+
+    pub fn opposite(self) -> Self {
+        Direction::ALL[(self.index() + 2) % 4]
+    }
+
+    // how puzzles draw it
+    pub fn arrow(self) -> char {
+        match self {
+            Direction::Up => '^',
+            Direction::Right => '>',
+            Direction::Down => 'v',
+            Direction::Left => '<',
+        }
+    }
+}
+
+// extended euclidean algorithm. returns (g, x, y) where a * x + b * y = g = gcd(a, b)
+pub fn ext_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    if b == 0 {
+        (a.abs(), a.signum(), 0)
+    } else {
+        let (g, x, y) = ext_gcd(b, a.rem_euclid(b));
+        (g, y, x - a.div_euclid(b) * y)
+    }
+}
+
+// solves t ≡ a1 (mod m1) and t ≡ a2 (mod m2) for t. the moduli don't have to
+// be coprime, so there might not be an answer. returns (t, lcm(m1, m2)) where
+// 0 <= t < lcm
+pub fn crt((a1, m1): (i64, i64), (a2, m2): (i64, i64)) -> Option<(i64, i64)> {
+    let (a1, m1, a2, m2) = (a1 as i128, m1 as i128, a2 as i128, m2 as i128);
+    let (g, p, _) = ext_gcd(m1, m2);
+    if (a2 - a1) % g != 0 {
+        return None;
+    }
+    let lcm = m1 / g * m2;
+    // t = a1 + m1 * k, where m1 * k ≡ a2 - a1 (mod m2)
+    let k = ((a2 - a1) / g * p).rem_euclid(m2 / g);
+    let t = (a1 + m1 * k).rem_euclid(lcm);
+    Some((t.try_into().ok()?, lcm.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chinese_remainder() {
+        assert_eq!(crt((2, 3), (3, 5)), Some((8, 15)));
+        assert_eq!(crt((0, 101), (0, 103)), Some((0, 10403)));
+        assert_eq!(crt((5, 101), (99, 103)), Some((5661, 10403)));
+        // not coprime
+        assert_eq!(crt((1, 4), (3, 6)), Some((9, 12)));
+        assert_eq!(crt((1, 4), (2, 6)), None);
+        assert_eq!(ext_gcd(240, 46), (2, -9, 47));
+    }
+}
@@ -0,0 +1,252 @@
+use std::ops::{Bound, RangeBounds, RangeInclusive};
+
+// a set of u64s stored as sorted inclusive ranges. ranges never overlap or
+// touch, so 1..=3 and 4..=5 get stored as 1..=5. anything implementing
+// RangeBounds can go in, so both a..b and a..=b work
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RangeSet {
+    ranges: Vec<(u64, u64)>,
+}
+
+// turns any range into inclusive bounds, or None if it's empty
+fn inclusive(range: impl RangeBounds<u64>) -> Option<(u64, u64)> {
+    let start = match range.start_bound() {
+        Bound::Included(&start) => start,
+        Bound::Excluded(&start) => start.checked_add(1)?,
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(&end) => end,
+        Bound::Excluded(&end) => end.checked_sub(1)?,
+        Bound::Unbounded => u64::MAX,
+    };
+    (start <= end).then_some((start, end))
+}
+
+impl RangeSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, range: impl RangeBounds<u64>) {
+        let Some((mut start, mut end)) = inclusive(range) else {
+            return;
+        };
+        // everything overlapping or touching the new range gets merged into it
+        let first = self
+            .ranges
+            .partition_point(|r| r.1.saturating_add(1) < start);
+        let last = self
+            .ranges
+            .partition_point(|r| r.0 <= end.saturating_add(1));
+        if first < last {
+            start = start.min(self.ranges[first].0);
+            end = end.max(self.ranges[last - 1].1);
+        }
+        self.ranges.splice(first..last, [(start, end)]);
+    }
+
+    pub fn remove(&mut self, range: impl RangeBounds<u64>) {
+        let Some((start, end)) = inclusive(range) else {
+            return;
+        };
+        let first = self.ranges.partition_point(|r| r.1 < start);
+        let last = self.ranges.partition_point(|r| r.0 <= end);
+        if first == last {
+            return;
+        }
+        // the ranges at the edges might stick out of the removed part
+        let mut leftovers = Vec::with_capacity(2);
+        if self.ranges[first].0 < start {
+            leftovers.push((self.ranges[first].0, start - 1));
+        }
+        if self.ranges[last - 1].1 > end {
+            leftovers.push((end + 1, self.ranges[last - 1].1));
+        }
+        self.ranges.splice(first..last, leftovers);
+    }
+
+    pub fn contains(&self, value: u64) -> bool {
+        let i = self.ranges.partition_point(|r| r.1 < value);
+        self.ranges.get(i).is_some_and(|r| r.0 <= value)
+    }
+
+    // true if every value of the range is in the set
+    pub fn contains_range(&self, range: impl RangeBounds<u64>) -> bool {
+        let Some((start, end)) = inclusive(range) else {
+            return true;
+        };
+        let i = self.ranges.partition_point(|r| r.1 < start);
+        // since touching ranges are merged, it has to be inside a single one
+        self.ranges
+            .get(i)
+            .is_some_and(|r| r.0 <= start && end <= r.1)
+    }
+
+    // true if any value of the range is in the set
+    pub fn overlaps(&self, range: impl RangeBounds<u64>) -> bool {
+        let Some((start, end)) = inclusive(range) else {
+            return false;
+        };
+        let i = self.ranges.partition_point(|r| r.1 < start);
+        self.ranges.get(i).is_some_and(|r| r.0 <= end)
+    }
+
+    pub fn union(&self, other: &RangeSet) -> RangeSet {
+        let mut out = self.clone();
+        for &(start, end) in &other.ranges {
+            out.insert(start..=end);
+        }
+        out
+    }
+
+    pub fn intersect(&self, other: &RangeSet) -> RangeSet {
+        let mut ranges = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while i < self.ranges.len() && j < other.ranges.len() {
+            let (a, b) = (self.ranges[i], other.ranges[j]);
+            let (start, end) = (a.0.max(b.0), a.1.min(b.1));
+            if start <= end {
+                ranges.push((start, end));
+            }
+            // whichever ends first can't overlap anything else
+            if a.1 < b.1 {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        RangeSet { ranges }
+    }
+
+    pub fn difference(&self, other: &RangeSet) -> RangeSet {
+        let mut out = self.clone();
+        for &(start, end) in &other.ranges {
+            out.remove(start..=end);
+        }
+        out
+    }
+
+    // how many values are in the set. u128 because the full u64 range has one
+    // more value than fits in a u64
+    pub fn len(&self) -> u128 {
+        self.ranges
+            .iter()
+            .map(|&(start, end)| (end - start) as u128 + 1)
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = RangeInclusive<u64>> + '_ {
+        self.ranges.iter().map(|&(start, end)| start..=end)
+    }
+
+    // the parts of the range that aren't in the set
+    pub fn gaps(&self, within: impl RangeBounds<u64>) -> impl Iterator<Item = RangeInclusive<u64>> {
+        let mut gaps = RangeSet::new();
+        if let Some((start, end)) = inclusive(within) {
+            gaps.insert(start..=end);
+        }
+        gaps.difference(self)
+            .ranges
+            .into_iter()
+            .map(|(start, end)| start..=end)
+    }
+}
+
+impl<R: RangeBounds<u64>> FromIterator<R> for RangeSet {
+    fn from_iter<I: IntoIterator<Item = R>>(iter: I) -> Self {
+        let mut set = RangeSet::new();
+        for range in iter {
+            set.insert(range);
+        }
+        set
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_merges() {
+        let mut set = RangeSet::new();
+        set.insert(10..=14);
+        set.insert(3..=5);
+        set.insert(16..=20);
+        assert_eq!(
+            set.iter().collect::<Vec<_>>(),
+            vec![3..=5, 10..=14, 16..=20]
+        );
+        set.insert(12..=18);
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![3..=5, 10..=20]);
+        // touching ranges merge too, and half open ranges work
+        set.insert(6..10);
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![3..=20]);
+        assert_eq!(set.len(), 18);
+        // empty ranges do nothing
+        set.insert(30..30);
+        assert_eq!(set.len(), 18);
+    }
+
+    #[test]
+    fn remove_splits() {
+        let mut set: RangeSet = [0..=9, 20..=29].into_iter().collect();
+        set.remove(5..25);
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![0..=4, 25..=29]);
+        set.remove(0..=4);
+        set.remove(27..=27);
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![25..=26, 28..=29]);
+        set.remove(..);
+        assert!(set.is_empty());
+    }
+
+    #[test]
+    fn membership() {
+        let set: RangeSet = [3..=5, 10..=14, 16..=20, 12..=18].into_iter().collect();
+        let hits = [1, 5, 8, 11, 17, 32]
+            .into_iter()
+            .filter(|&id| set.contains(id))
+            .collect::<Vec<_>>();
+        assert_eq!(hits, vec![5, 11, 17]);
+        assert!(set.contains_range(10..=20));
+        assert!(!set.contains_range(4..=10));
+        assert!(set.overlaps(6..=10));
+        assert!(!set.overlaps(6..10));
+    }
+
+    #[test]
+    fn set_operations() {
+        let a: RangeSet = [0..10, 20..30].into_iter().collect();
+        let b: RangeSet = std::iter::once(5..25).collect();
+        let ranges = |set: RangeSet| set.iter().collect::<Vec<_>>();
+        assert_eq!(ranges(a.union(&b)), vec![0..=29]);
+        assert_eq!(ranges(a.intersect(&b)), vec![5..=9, 20..=24]);
+        assert_eq!(ranges(a.difference(&b)), vec![0..=4, 25..=29]);
+        assert_eq!(ranges(b.difference(&a)), vec![10..=19]);
+    }
+
+    #[test]
+    fn gaps() {
+        let set: RangeSet = [3..=5, 10..=14].into_iter().collect();
+        assert_eq!(
+            set.gaps(0..=20).collect::<Vec<_>>(),
+            vec![0..=2, 6..=9, 15..=20]
+        );
+        assert_eq!(set.gaps(4..12).collect::<Vec<_>>(), vec![6..=9]);
+    }
+
+    #[test]
+    fn extremes() {
+        let mut set = RangeSet::new();
+        set.insert(..);
+        assert_eq!(set.len(), u64::MAX as u128 + 1);
+        set.remove(u64::MAX..);
+        assert!(!set.contains(u64::MAX));
+        set.insert(u64::MAX..=u64::MAX);
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![0..=u64::MAX]);
+    }
+}
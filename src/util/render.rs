@@ -0,0 +1,621 @@
+use std::{
+    fmt::Write as _,
+    fs::{self, File},
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{
+        Mutex, OnceLock,
+        mpsc::{self, Receiver, RecvTimeoutError},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use clap::ValueEnum;
+
+// frames are just grids of characters. colours for image output are derived
+// from the characters so that solvers only have to think about one thing
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Frame {
+    width: usize,
+    height: usize,
+    glyphs: Vec<char>,
+}
+
+impl Frame {
+    pub fn new(width: usize, height: usize) -> Self {
+        Frame {
+            width,
+            height,
+            glyphs: vec!['.'; width * height],
+        }
+    }
+
+    pub fn from_grid<R: AsRef<[T]>, T>(grid: &[R], glyph: impl Fn(&T) -> char) -> Self {
+        let height = grid.len();
+        let width = grid.iter().map(|row| row.as_ref().len()).max().unwrap_or(0);
+        let mut frame = Frame::new(width, height);
+        for (y, row) in grid.iter().enumerate() {
+            for (x, elem) in row.as_ref().iter().enumerate() {
+                frame.set((y, x), glyph(elem));
+            }
+        }
+        frame
+    }
+
+    // points are (y, x) like everywhere else. points outside of the frame are
+    // ignored
+    pub fn from_points(
+        width: usize,
+        height: usize,
+        points: impl IntoIterator<Item = (usize, usize)>,
+        glyph: char,
+    ) -> Self {
+        let mut frame = Frame::new(width, height);
+        for point in points {
+            frame.set(point, glyph);
+        }
+        frame
+    }
+
+    pub fn set(&mut self, (y, x): (usize, usize), glyph: char) {
+        if y < self.height && x < self.width {
+            self.glyphs[y * self.width + x] = glyph;
+        }
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[char]> {
+        // chunks() panics on 0
+        self.glyphs.chunks(self.width.max(1))
+    }
+
+    pub fn to_ascii(&self) -> String {
+        let mut out = String::with_capacity((self.width + 1) * self.height);
+        for row in self.rows() {
+            out.extend(row);
+            out.push('\n');
+        }
+        out
+    }
+
+    // returns (width, height, rgb bytes) where every cell is a scale x scale
+    // square of pixels
+    pub fn to_rgb(&self, scale: usize) -> (usize, usize, Vec<u8>) {
+        let (w, h) = (self.width * scale, self.height * scale);
+        let mut pixels = Vec::with_capacity(w * h * 3);
+        for row in self.rows() {
+            let line = row
+                .iter()
+                .flat_map(|&c| std::iter::repeat_n(glyph_color(c), scale))
+                .flatten()
+                .collect::<Vec<u8>>();
+            for _ in 0..scale {
+                pixels.extend_from_slice(&line);
+            }
+        }
+        (w, h, pixels)
+    }
+}
+
+pub type Rgb = [u8; 3];
+
+pub fn glyph_color(c: char) -> Rgb {
+    match c {
+        '.' | ' ' => [16, 16, 24],
+        '#' => [128, 128, 140],
+        'O' | '[' | ']' => [196, 140, 64],
+        '@' | 'S' | 'E' => [230, 60, 60],
+        '^' | '>' | 'v' | '<' => [240, 220, 60],
+        'X' | 'x' | '*' | '+' => [80, 200, 120],
+        '0'..='9' => {
+            // a gradient for counts and ids
+            let v = c.to_digit(10).unwrap() as u8;
+            [40 + v * 20, 80 + v * 15, 200]
+        }
+        _ => {
+            // anything else gets some stable but arbitrary colour
+            let h = (c as u32).wrapping_mul(2654435761);
+            [
+                64 + (h >> 24) as u8 % 192,
+                64 + (h >> 16) as u8 % 192,
+                64 + (h >> 8) as u8 % 192,
+            ]
+        }
+    }
+}
+
+pub fn encode_ppm(width: usize, height: usize, rgb: &[u8]) -> Vec<u8> {
+    assert_eq!(rgb.len(), width * height * 3);
+    let mut out = format!("P6\n{width} {height}\n255\n").into_bytes();
+    out.extend_from_slice(rgb);
+    out
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    // bitwise crc. slow, but images are small and this only runs when asked for
+    let mut crc = 0xffff_ffffu32;
+    for &b in bytes {
+        crc ^= u32::from(b);
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in bytes {
+        a = (a + u32::from(byte)) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+fn png_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let crc = crc32(&out[start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+// 8-bit truecolour png. the zlib stream only uses stored (uncompressed) deflate
+// blocks, which every decoder supports and which keeps this tiny
+pub fn encode_png(width: usize, height: usize, rgb: &[u8]) -> Vec<u8> {
+    assert_eq!(rgb.len(), width * height * 3);
+    let mut out = vec![0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend_from_slice(&(width as u32).to_be_bytes());
+    ihdr.extend_from_slice(&(height as u32).to_be_bytes());
+    // bit depth 8, colour type 2 (rgb), default compression, filter, no interlace
+    ihdr.extend_from_slice(&[8, 2, 0, 0, 0]);
+    png_chunk(&mut out, b"IHDR", &ihdr);
+
+    // every scanline is prefixed with filter type 0 (none)
+    let mut raw = Vec::with_capacity((width * 3 + 1) * height);
+    for line in rgb.chunks(width * 3).filter(|_| width > 0) {
+        raw.push(0);
+        raw.extend_from_slice(line);
+    }
+
+    let mut zlib = vec![0x78, 0x01];
+    let mut blocks = raw.chunks(u16::MAX as usize).peekable();
+    if blocks.peek().is_none() {
+        // an empty final block
+        zlib.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        zlib.push(u8::from(last));
+        let len = block.len() as u16;
+        zlib.extend_from_slice(&len.to_le_bytes());
+        zlib.extend_from_slice(&(!len).to_le_bytes());
+        zlib.extend_from_slice(block);
+    }
+    zlib.extend_from_slice(&adler32(&raw).to_be_bytes());
+    png_chunk(&mut out, b"IDAT", &zlib);
+
+    png_chunk(&mut out, b"IEND", &[]);
+    out
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, ValueEnum)]
+pub enum FrameFormat {
+    Ascii,
+    Ppm,
+    Png,
+}
+
+#[derive(Clone, Debug)]
+pub enum Output {
+    // frames are written to <dir>/<name>/frame_00000.<ext>
+    Files {
+        dir: PathBuf,
+        format: FrameFormat,
+        // pixels per cell for images
+        scale: usize,
+    },
+    // frames are drawn in the terminal at this rate
+    Terminal {
+        fps: f64,
+    },
+}
+
+#[derive(Clone, Debug)]
+pub struct VisualizeConfig {
+    pub output: Output,
+    // only every nth frame is shown
+    pub stride: usize,
+}
+
+static CONFIG: OnceLock<VisualizeConfig> = OnceLock::new();
+
+// called once by the runner. solvers never see frames otherwise, so tests and
+// benchmarks are unaffected
+pub fn init(config: VisualizeConfig) {
+    assert!(config.stride > 0);
+    match config.output {
+        Output::Files { scale, .. } => assert!(scale > 0),
+        Output::Terminal { fps } => assert!(fps > 0.0),
+    }
+    CONFIG
+        .set(config)
+        .expect("visualization was initialised twice");
+}
+
+enum Sink {
+    Files {
+        dir: PathBuf,
+        format: FrameFormat,
+        scale: usize,
+        written: usize,
+    },
+    Terminal(Player),
+}
+
+// a named sequence of frames from a solver
+pub struct Visualizer {
+    name: String,
+    sink: Sink,
+    stride: usize,
+    seen: usize,
+}
+
+impl Visualizer {
+    // returns None when visualization isn't enabled, so solvers can just do
+    // `if let Some(vis) = &mut vis`
+    pub fn open(name: &str) -> Option<Self> {
+        let config = CONFIG.get()?;
+        Some(Self::with_config(config, name))
+    }
+
+    fn with_config(config: &VisualizeConfig, name: &str) -> Self {
+        let sink = match &config.output {
+            Output::Files { dir, format, scale } => {
+                let dir = dir.join(name);
+                fs::create_dir_all(&dir)
+                    .unwrap_or_else(|e| panic!("could not create frame directory {dir:?}: {e}"));
+                Sink::Files {
+                    dir,
+                    format: *format,
+                    scale: *scale,
+                    written: 0,
+                }
+            }
+            Output::Terminal { fps } => Sink::Terminal(Player::new(*fps)),
+        };
+        Visualizer {
+            name: name.to_string(),
+            sink,
+            stride: config.stride,
+            seen: 0,
+        }
+    }
+
+    pub fn frame(&mut self, frame: &Frame) {
+        let skip = !self.seen.is_multiple_of(self.stride);
+        self.seen += 1;
+        if skip {
+            return;
+        }
+        match &mut self.sink {
+            Sink::Files {
+                dir,
+                format,
+                scale,
+                written,
+            } => {
+                let (ext, bytes) = match format {
+                    FrameFormat::Ascii => ("txt", frame.to_ascii().into_bytes()),
+                    FrameFormat::Ppm => {
+                        let (w, h, rgb) = frame.to_rgb(*scale);
+                        ("ppm", encode_ppm(w, h, &rgb))
+                    }
+                    FrameFormat::Png => {
+                        let (w, h, rgb) = frame.to_rgb(*scale);
+                        ("png", encode_png(w, h, &rgb))
+                    }
+                };
+                let path = dir.join(format!("frame_{:05}.{ext}", written));
+                write_file(&path, &bytes);
+                *written += 1;
+            }
+            Sink::Terminal(player) => player.play(&self.name, frame),
+        }
+    }
+}
+
+fn write_file(path: &Path, bytes: &[u8]) {
+    fs::write(path, bytes).unwrap_or_else(|e| panic!("could not write frame {path:?}: {e}"));
+}
+
+// ctrl-c while the terminal is in raw mode
+const INTERRUPT: u8 = 0x03;
+
+// keypresses from the terminal. there is only ever one reader thread, as a
+// thread blocked on a read can't be stopped and would eat keys meant for the
+// next visualizer
+fn keys() -> Option<&'static Mutex<Receiver<u8>>> {
+    static KEYS: OnceLock<Option<Mutex<Receiver<u8>>>> = OnceLock::new();
+    KEYS.get_or_init(|| {
+        let mut tty = File::open("/dev/tty").ok()?;
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let mut buf = [0];
+            while tty.read_exact(&mut buf).is_ok() {
+                if buf[0] == INTERRUPT {
+                    // exiting skips Drop for the player, so put the terminal
+                    // back ourselves. this is handled here rather than in the
+                    // player so that it also works while a solver is busy
+                    // between frames
+                    restore_terminal(true);
+                    std::process::exit(130);
+                }
+                if tx.send(buf[0]).is_err() {
+                    break;
+                }
+            }
+        });
+        Some(Mutex::new(rx))
+    })
+    .as_ref()
+}
+
+fn stty(args: &[&str]) -> bool {
+    // std has no way of turning off line buffering, so we borrow stty for it
+    File::open("/dev/tty")
+        .and_then(|tty| Command::new("stty").args(args).stdin(tty).status())
+        .is_ok_and(|status| status.success())
+}
+
+fn restore_terminal(raw: bool) {
+    // show the cursor again
+    print!("\x1b[0m\x1b[?25h");
+    io::stdout().flush().unwrap();
+    if raw {
+        stty(&["icanon", "echo", "isig"]);
+    }
+}
+
+// what a keypress does to the player
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Action {
+    Ignore,
+    Redraw,
+    NextFrame,
+}
+
+// draws frames in the terminal with ansi colours. space pauses, n steps a
+// single frame while paused, + and - change the speed and q skips the rest of
+// the frames. the terminal is put in raw mode without signals so that keys
+// arrive immediately, which means ctrl-c is read as a key too (see keys())
+struct Player {
+    frame_time: Duration,
+    // whether stty settings have to be restored
+    raw: bool,
+    paused: bool,
+    skipping: bool,
+    shown: usize,
+}
+
+impl Player {
+    fn new(fps: f64) -> Self {
+        let raw = keys().is_some() && stty(&["-icanon", "-echo", "-isig"]);
+        // hide the cursor and clear the screen
+        print!("\x1b[?25l\x1b[2J");
+        Player {
+            frame_time: Duration::from_secs_f64(1.0 / fps),
+            raw,
+            paused: false,
+            skipping: false,
+            shown: 0,
+        }
+    }
+
+    fn play(&mut self, name: &str, frame: &Frame) {
+        if self.skipping {
+            return;
+        }
+        self.shown += 1;
+        self.draw(name, frame);
+        let deadline = Instant::now() + self.frame_time;
+        let Some(keys) = keys() else {
+            thread::sleep(self.frame_time);
+            return;
+        };
+        let keys = keys.lock().unwrap();
+        self.wait(&keys, deadline, |player| player.draw(name, frame));
+    }
+
+    // handles keys until it's time for the next frame
+    fn wait(&mut self, keys: &Receiver<u8>, deadline: Instant, mut redraw: impl FnMut(&Self)) {
+        loop {
+            let key = if self.paused {
+                keys.recv().map_err(RecvTimeoutError::from)
+            } else {
+                keys.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+            };
+            match key {
+                Ok(key) => match self.press(key) {
+                    Action::Ignore => {}
+                    // show the new state
+                    Action::Redraw => redraw(self),
+                    Action::NextFrame => return,
+                },
+                Err(RecvTimeoutError::Timeout) => return,
+                Err(RecvTimeoutError::Disconnected) => {
+                    // no more keys, so just play everything
+                    self.paused = false;
+                    thread::sleep(deadline.saturating_duration_since(Instant::now()));
+                    return;
+                }
+            }
+        }
+    }
+
+    fn press(&mut self, key: u8) -> Action {
+        match key {
+            b' ' => self.paused = !self.paused,
+            b'n' if self.paused => return Action::NextFrame,
+            b'+' => self.frame_time = self.frame_time.div_f64(1.5),
+            b'-' => self.frame_time = self.frame_time.mul_f64(1.5),
+            b'q' => {
+                self.skipping = true;
+                return Action::NextFrame;
+            }
+            _ => return Action::Ignore,
+        }
+        Action::Redraw
+    }
+
+    fn draw(&self, name: &str, frame: &Frame) {
+        let mut out = String::from("\x1b[H");
+        let mut last_color = None;
+        for row in frame.rows() {
+            for &c in row {
+                let color = match c {
+                    // empty space has to be visible on a dark terminal
+                    '.' => [70, 70, 80],
+                    _ => glyph_color(c),
+                };
+                if last_color != Some(color) {
+                    let [r, g, b] = color;
+                    write!(out, "\x1b[38;2;{r};{g};{b}m").unwrap();
+                    last_color = Some(color);
+                }
+                out.push(c);
+            }
+            out.push_str("\x1b[K\n");
+        }
+        let fps = 1.0 / self.frame_time.as_secs_f64();
+        let state = if self.paused { " (paused)" } else { "" };
+        write!(
+            out,
+            "\x1b[0m{name} frame {} at {fps:.1} fps{state}\x1b[K\n\
+             space: pause, n: step, +/-: speed, q: skip\x1b[K\n",
+            self.shown
+        )
+        .unwrap();
+        let mut stdout = io::stdout().lock();
+        stdout.write_all(out.as_bytes()).unwrap();
+        stdout.flush().unwrap();
+    }
+}
+
+impl Drop for Player {
+    fn drop(&mut self) {
+        restore_terminal(self.raw);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksums() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+    }
+
+    #[test]
+    fn frames() {
+        let frame = Frame::from_points(3, 2, [(0, 0), (1, 2), (5, 5)], '#');
+        assert_eq!(frame.to_ascii(), "#..\n..#\n");
+
+        let grid = vec![vec![true, false], vec![false, true]];
+        let frame = Frame::from_grid(&grid, |&b| if b { 'O' } else { '.' });
+        assert_eq!(frame.to_ascii(), "O.\n.O\n");
+
+        let (w, h, rgb) = frame.to_rgb(3);
+        assert_eq!((w, h, rgb.len()), (6, 6, 6 * 6 * 3));
+        assert_eq!(rgb[0..3], glyph_color('O'));
+    }
+
+    #[test]
+    fn png_layout() {
+        let frame = Frame::from_points(2, 2, [(0, 1)], '#');
+        let (w, h, rgb) = frame.to_rgb(1);
+        let png = encode_png(w, h, &rgb);
+        assert_eq!(&png[1..4], b"PNG");
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(&png[png.len() - 8..png.len() - 4], b"IEND");
+        // 2 rows of filter byte + 6 colour bytes in a single stored block
+        let idat_len = u32::from_be_bytes(png[33..37].try_into().unwrap());
+        assert_eq!(idat_len as usize, 2 + 5 + 2 * 7 + 4);
+
+        let ppm = encode_ppm(w, h, &rgb);
+        assert!(ppm.starts_with(b"P6\n2 2\n255\n"));
+        assert_eq!(ppm.len(), 11 + 12);
+    }
+
+    // a player that doesn't touch the terminal until it is dropped
+    fn player(fps: f64) -> Player {
+        Player {
+            frame_time: Duration::from_secs_f64(1.0 / fps),
+            raw: false,
+            paused: false,
+            skipping: false,
+            shown: 0,
+        }
+    }
+
+    #[test]
+    fn player_keys() {
+        let mut player = player(10.0);
+        assert_eq!(player.press(b'x'), Action::Ignore);
+        // n only steps while paused
+        assert_eq!(player.press(b'n'), Action::Ignore);
+        assert_eq!(player.press(b' '), Action::Redraw);
+        assert!(player.paused);
+        assert_eq!(player.press(b'n'), Action::NextFrame);
+        assert!(player.paused);
+
+        assert_eq!(player.press(b'+'), Action::Redraw);
+        assert!(player.frame_time < Duration::from_millis(100));
+        assert_eq!(player.press(b'-'), Action::Redraw);
+        assert!(player.frame_time.abs_diff(Duration::from_millis(100)) < Duration::from_micros(1));
+
+        assert_eq!(player.press(b'q'), Action::NextFrame);
+        assert!(player.skipping);
+        // skipped frames aren't drawn at all
+        player.play("test", &Frame::new(1, 1));
+        assert_eq!(player.shown, 0);
+    }
+
+    #[test]
+    fn player_stepping() {
+        let mut player = player(1000.0);
+        let (tx, rx) = mpsc::channel();
+        let mut redraws = 0;
+
+        // pausing waits for keys past the deadline until n steps a frame
+        for key in [b' ', b'+', b'x', b'n'] {
+            tx.send(key).unwrap();
+        }
+        player.wait(&rx, Instant::now(), |_| redraws += 1);
+        assert!(player.paused);
+        assert_eq!(redraws, 2);
+        assert!(rx.try_recv().is_err());
+
+        // without keys the frame ends at the deadline
+        player.paused = false;
+        player.wait(&rx, Instant::now(), |_| redraws += 1);
+        assert_eq!(redraws, 2);
+
+        // when the keys run out a paused player plays everything
+        for key in [b' ', b'-'] {
+            tx.send(key).unwrap();
+        }
+        drop(tx);
+        player.wait(&rx, Instant::now(), |_| redraws += 1);
+        assert!(!player.paused);
+        assert_eq!(redraws, 4);
+    }
+}
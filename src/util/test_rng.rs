@@ -0,0 +1,20 @@
+// small lcg so tests don't need a rng crate. the constants are knuth's, and the
+// low bits are thrown away because they repeat quickly
+pub struct TestRng {
+    state: u64,
+}
+
+impl TestRng {
+    pub fn new(seed: u64) -> Self {
+        TestRng { state: seed }
+    }
+
+    // a number in 0..below
+    pub fn below(&mut self, below: u64) -> u64 {
+        self.state = self
+            .state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (self.state >> 33) % below
+    }
+}
@@ -0,0 +1,75 @@
+pub struct UnionFind {
+    parents: Vec<usize>,
+    ranks: Vec<usize>,
+    set_count: usize,
+}
+
+// https://www.geeksforgeeks.org/dsa/introduction-to-disjoint-set-data-structure-or-union-find-algorithm/
+impl UnionFind {
+    pub fn new(count: usize) -> Self {
+        Self {
+            parents: Vec::from_iter(0..count),
+            ranks: vec![0; count],
+            set_count: count,
+        }
+    }
+
+    pub fn find(&mut self, v: usize) -> usize {
+        let root = self.parents[v];
+        // compress
+        if self.parents[root] != root {
+            self.parents[v] = self.find(root);
+            return self.parents[v];
+        }
+        root
+    }
+    // returns false if they were already united
+    pub fn unite(&mut self, x: usize, y: usize) -> bool {
+        let x_root = self.find(x);
+        let y_root = self.find(y);
+
+        // already united
+        if x_root == y_root {
+            return false;
+        }
+
+        self.set_count -= 1;
+        if self.ranks[x_root] < self.ranks[y_root] {
+            self.parents[x_root] = y_root;
+        } else if self.ranks[x_root] > self.ranks[y_root] {
+            self.parents[y_root] = x_root;
+        } else {
+            self.parents[y_root] = x_root;
+            self.ranks[x_root] += 1;
+        }
+        true
+    }
+
+    pub fn connected(&mut self, x: usize, y: usize) -> bool {
+        self.find(x) == self.find(y)
+    }
+
+    pub fn are_all_merged(&mut self) -> bool {
+        self.set_count == 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merging() {
+        let mut uf = UnionFind::new(5);
+        assert!(uf.unite(0, 1));
+        assert!(uf.unite(3, 4));
+        assert!(!uf.unite(1, 0));
+        assert!(uf.connected(0, 1));
+        assert!(!uf.connected(1, 3));
+        assert!(!uf.are_all_merged());
+        assert!(uf.unite(1, 2));
+        assert!(uf.unite(2, 4));
+        assert!(uf.connected(0, 3));
+        assert!(uf.are_all_merged());
+    }
+}